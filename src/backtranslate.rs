@@ -0,0 +1,357 @@
+use crate::codons::{self, CodonTable, Organism};
+use crate::compute_gc_content;
+use crate::digest::{self, Enzyme};
+use crate::fasta;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Limits the search for a sequence that satisfies every constraint, since impossible
+/// combinations would otherwise take exponential time to rule out.
+const MAX_BACKTRACKS: usize = 100_000;
+
+pub struct BackTranslateOptions {
+    pub min_gc: f64,
+    pub max_gc: f64,
+    pub max_homopolymer: usize,
+    pub min_codon_weight: f64,
+    pub forbidden_enzymes: Vec<String>,
+}
+
+fn confirm_valid_protein(protein: &[u8]) -> Result<()> {
+    for (i, aa) in protein.iter().enumerate() {
+        if codons::synonymous_codons(*aa).is_empty() {
+            bail!("Invalid amino acid: '{}' at position {i}", *aa as char);
+        }
+    }
+    Ok(())
+}
+
+fn gc_count(seq: &[u8]) -> usize {
+    seq.iter().filter(|b| matches!(b, b'G' | b'C')).count()
+}
+
+fn allowed_codons(amino_acid: u8, weights: &[f64; 64], opts: &BackTranslateOptions) -> Vec<usize> {
+    codons::synonymous_codons(amino_acid)
+        .into_iter()
+        .filter(|i| weights[*i] >= opts.min_codon_weight || weights[*i] == 1.0)
+        .collect()
+}
+
+/// The fewest and most Gs and Cs that the allowed codons can contribute from each position of
+/// the protein to its end.
+fn remaining_gc_bounds(
+    protein: &[u8],
+    weights: &[f64; 64],
+    opts: &BackTranslateOptions,
+) -> Vec<(usize, usize)> {
+    let mut bounds = vec![(0, 0); protein.len() + 1];
+    for (position, amino_acid) in protein.iter().enumerate().rev() {
+        let codon_gcs = allowed_codons(*amino_acid, weights, opts)
+            .into_iter()
+            .map(|i| gc_count(codons::codon_at(i).as_bytes()));
+        let (fewest, most) = codon_gcs.fold((usize::MAX, 0), |(fewest, most), gc| {
+            (fewest.min(gc), most.max(gc))
+        });
+        let (rest_fewest, rest_most) = bounds[position + 1];
+        bounds[position] = (fewest + rest_fewest, most + rest_most);
+    }
+    bounds
+}
+
+/// Codons for an amino acid, best first. Codons are ranked by how often they're used in the
+/// reference, except that while the sequence so far is outside of the GC bounds, codons that
+/// move it back towards them are tried first.
+fn candidates(
+    amino_acid: u8,
+    seq: &[u8],
+    weights: &[f64; 64],
+    opts: &BackTranslateOptions,
+) -> Vec<usize> {
+    let mut candidates = allowed_codons(amino_acid, weights, opts);
+    candidates.sort_by(|a, b| weights[*b].total_cmp(&weights[*a]));
+    if !seq.is_empty() {
+        let gc = gc_count(seq) as f64 / seq.len() as f64;
+        let codon_gc = |i: &usize| gc_count(codons::codon_at(*i).as_bytes());
+        if gc > opts.max_gc {
+            candidates.sort_by_key(codon_gc);
+        } else if gc < opts.min_gc {
+            candidates.sort_by_key(|i| std::cmp::Reverse(codon_gc(i)));
+        }
+    }
+    candidates
+}
+
+/// Whether the last bases of a sequence are free of long homopolymers and forbidden sites. Only
+/// the end needs to be checked, since the rest has been checked already.
+fn has_allowed_end(seq: &[u8], max_homopolymer: usize, enzymes: &[&'static Enzyme]) -> bool {
+    let tail = &seq[seq.len().saturating_sub(max_homopolymer + 3)..];
+    if tail
+        .chunk_by(|a, b| a == b)
+        .any(|run| run.len() > max_homopolymer)
+    {
+        return false;
+    }
+    enzymes.iter().all(|enzyme| {
+        let tail_start = seq.len().saturating_sub(enzyme.site.len() + 2);
+        digest::find_recognition_sites(enzyme, &seq[tail_start..], false).is_empty()
+    })
+}
+
+/// Chooses a codon for each amino acid, backtracking whenever no codon can be added without
+/// creating a homopolymer or restriction site, or leaving the rest of the protein unable to
+/// bring the GC content within bounds.
+pub fn design(
+    protein: &[u8],
+    table: &CodonTable,
+    opts: &BackTranslateOptions,
+    enzymes: &[&'static Enzyme],
+) -> Result<String> {
+    confirm_valid_protein(protein)?;
+    let weights = table.weights();
+    for amino_acid in protein {
+        if allowed_codons(*amino_acid, &weights, opts).is_empty() {
+            bail!(
+                "No codon for amino acid '{}' passes --min-codon-weight",
+                *amino_acid as char
+            );
+        }
+    }
+    let gc_bounds = remaining_gc_bounds(protein, &weights, opts);
+    let length = protein.len() * 3;
+    // Whether a sequence with this many Gs and Cs so far can still end up within the GC bounds.
+    // This uses the same arithmetic as `compute_gc_content`, which checks the final sequence.
+    let gc_content = |gc: usize| gc as f32 / length as f32;
+    let can_meet_gc = |gc: usize, (fewest, most): (usize, usize)| {
+        length == 0
+            || (gc_content(gc + most) >= opts.min_gc as f32
+                && gc_content(gc + fewest) <= opts.max_gc as f32)
+    };
+    if !can_meet_gc(0, gc_bounds[0]) {
+        bail!(
+            "No back-translation has a GC content within {}-{}",
+            opts.min_gc,
+            opts.max_gc
+        );
+    }
+    let mut seq: Vec<u8> = vec![];
+    // The codons to try at each position, and the index of the next one to try.
+    let mut frames: Vec<(Vec<usize>, usize)> = vec![];
+    let mut backtracks = 0;
+    loop {
+        let position = seq.len() / 3;
+        if position == protein.len() {
+            break;
+        }
+        if frames.len() == position {
+            frames.push((candidates(protein[position], &seq, &weights, opts), 0));
+        }
+        let (candidates, next) = frames.last_mut().expect("a frame for every codon");
+        let mut chosen = None;
+        while *next < candidates.len() && chosen.is_none() {
+            let codon = codons::codon_at(candidates[*next]);
+            *next += 1;
+            let extended = [seq.as_slice(), codon.as_bytes()].concat();
+            if can_meet_gc(gc_count(&extended), gc_bounds[position + 1])
+                && has_allowed_end(&extended, opts.max_homopolymer, enzymes)
+            {
+                chosen = Some(codon);
+            }
+        }
+        match chosen {
+            Some(codon) => seq.extend(codon.bytes()),
+            None => {
+                frames.pop();
+                backtracks += 1;
+                if frames.is_empty() || backtracks > MAX_BACKTRACKS {
+                    bail!(
+                        "Unable to back-translate within the GC bounds without a forbidden site \
+                         or long homopolymer"
+                    );
+                }
+                seq.truncate(seq.len() - 3);
+            }
+        }
+    }
+    let seq = String::from_utf8(seq)?;
+    if !seq.is_empty() {
+        let gc = compute_gc_content(vec![seq.clone()])?;
+        if gc < opts.min_gc as f32 || gc > opts.max_gc as f32 {
+            bail!(
+                "Back-translated sequence has a GC content of {:.4}, outside of {}-{}",
+                gc,
+                opts.min_gc,
+                opts.max_gc
+            );
+        }
+    }
+    Ok(seq)
+}
+
+pub fn back_translate(
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    organism: Organism,
+    table: Option<&Path>,
+    opts: BackTranslateOptions,
+) -> Result<String> {
+    let table = match table {
+        Some(path) => CodonTable::from_file(path)?,
+        None => CodonTable::for_organism(organism),
+    };
+    if opts.min_gc > opts.max_gc {
+        bail!("Minimum GC content must not be greater than the maximum");
+    }
+    if opts.max_homopolymer == 0 {
+        bail!("Maximum homopolymer length must be at least 1");
+    }
+    let enzymes = opts
+        .forbidden_enzymes
+        .iter()
+        .map(|name| digest::find_enzyme(name))
+        .collect::<Result<Vec<_>>>()?;
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let mut output = vec![];
+    for record in records {
+        let protein = record.seq.to_ascii_uppercase().into_bytes();
+        let dna = design(&protein, &table, &opts, &enzymes)?;
+        if record.id.is_empty() {
+            output.push(dna);
+        } else {
+            output.push(format!(">{}\n{}", record.id, dna));
+        }
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(dna: &str) -> String {
+        dna.as_bytes()
+            .chunks(3)
+            .map(|codon| codons::amino_acid(codons::codon_index(codon).unwrap()) as char)
+            .collect()
+    }
+
+    #[test]
+    fn test_uses_preferred_codons() {
+        let table = CodonTable::for_organism(Organism::Ecoli);
+        let opts = BackTranslateOptions {
+            min_gc: 0.0,
+            max_gc: 1.0,
+            max_homopolymer: 5,
+            min_codon_weight: 0.0,
+            forbidden_enzymes: vec![],
+        };
+        let dna = design(b"MLEK*", &table, &opts, &[]).unwrap();
+        assert_eq!(dna, "ATGCTGGAAAAATAA");
+    }
+
+    #[test]
+    fn test_avoids_homopolymers() {
+        let table = CodonTable::for_organism(Organism::Ecoli);
+        let opts = BackTranslateOptions {
+            min_gc: 0.0,
+            max_gc: 1.0,
+            max_homopolymer: 5,
+            min_codon_weight: 0.0,
+            forbidden_enzymes: vec![],
+        };
+        // AAA is the preferred lysine codon, so KKK would be a run of nine As.
+        let dna = design(b"KKK", &table, &opts, &[]).unwrap();
+        assert_eq!(translate(&dna), "KKK");
+        assert!(!dna.contains("AAAAAA"));
+    }
+
+    #[test]
+    fn test_avoids_forbidden_sites() {
+        let table = CodonTable::for_organism(Organism::Ecoli);
+        let opts = BackTranslateOptions {
+            min_gc: 0.0,
+            max_gc: 1.0,
+            max_homopolymer: 5,
+            min_codon_weight: 0.0,
+            forbidden_enzymes: vec![],
+        };
+        // HM would be CATATG (NdeI) with the preferred codons.
+        assert_eq!(design(b"HM", &table, &opts, &[]).unwrap(), "CATATG");
+        let ndei = digest::find_enzyme("NdeI").unwrap();
+        let dna = design(b"HM", &table, &opts, &[ndei]).unwrap();
+        assert_eq!(dna, "CACATG");
+    }
+
+    #[test]
+    fn test_gc_bounds() {
+        let table = CodonTable::for_organism(Organism::Yeast);
+        let opts = BackTranslateOptions {
+            min_gc: 0.4,
+            max_gc: 0.6,
+            max_homopolymer: 5,
+            min_codon_weight: 0.0,
+            forbidden_enzymes: vec![],
+        };
+        let dna = design(b"MKNIFLLYKDEIKQ", &table, &opts, &[]).unwrap();
+        assert_eq!(translate(&dna), "MKNIFLLYKDEIKQ");
+        let gc = compute_gc_content(vec![dna]).unwrap() as f64;
+        assert!((0.4..=0.6).contains(&gc));
+    }
+
+    #[test]
+    fn test_gc_bounds_with_high_gc_tail() {
+        let table = CodonTable::for_organism(Organism::Ecoli);
+        let opts = BackTranslateOptions {
+            min_gc: 0.0,
+            max_gc: 0.5,
+            max_homopolymer: 5,
+            min_codon_weight: 0.0,
+            forbidden_enzymes: vec![],
+        };
+        // Every glycine codon has at least two Gs, so the leucines need codons with no G or C,
+        // even though the first one alone would be within the bounds.
+        let dna = design(b"LLGGGG", &table, &opts, &[]).unwrap();
+        assert_eq!(translate(&dna), "LLGGGG");
+        assert!(dna.starts_with("TTATTA"));
+    }
+
+    #[test]
+    fn test_table_missing_amino_acid() {
+        // Only methionine and phenylalanine are used, so lysine's codons all have a weight of 0.
+        let mut usage = [0.0; 64];
+        usage[codons::codon_index(b"ATG").unwrap()] = 20.0;
+        usage[codons::codon_index(b"TTT").unwrap()] = 10.0;
+        let table = CodonTable { usage };
+        let opts = BackTranslateOptions {
+            min_gc: 0.0,
+            max_gc: 1.0,
+            max_homopolymer: 5,
+            min_codon_weight: 0.1,
+            forbidden_enzymes: vec![],
+        };
+        assert_eq!(design(b"MF", &table, &opts, &[]).unwrap(), "ATGTTT");
+        let err = design(b"MK", &table, &opts, &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No codon for amino acid 'K' passes --min-codon-weight"
+        );
+    }
+
+    #[test]
+    fn test_impossible_constraints() {
+        let table = CodonTable::for_organism(Organism::Ecoli);
+        let mut opts = BackTranslateOptions {
+            min_gc: 0.0,
+            max_gc: 1.0,
+            max_homopolymer: 1,
+            min_codon_weight: 0.0,
+            forbidden_enzymes: vec![],
+        };
+        // Every phenylalanine codon starts with TT.
+        assert!(design(b"F", &table, &opts, &[]).is_err());
+        opts.max_homopolymer = 5;
+        assert!(design(b"MXK", &table, &opts, &[]).is_err());
+        opts.max_gc = 0.5;
+        // Glycine codons are at least two thirds G or C.
+        assert!(design(b"GGG", &table, &opts, &[]).is_err());
+    }
+}
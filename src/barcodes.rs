@@ -0,0 +1,328 @@
+use crate::compute_gc_content;
+use crate::distance::{self, DistanceOptions, Metric};
+use crate::fasta;
+use anyhow::{bail, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::Path;
+
+/// Random candidates to try before giving up on finding another barcode, and sets to try before
+/// giving up on finding one that is color balanced.
+const MAX_ATTEMPTS: usize = 100_000;
+const MAX_RESTARTS: usize = 100;
+
+pub struct BarcodeOptions {
+    pub metric: Metric,
+    pub min_distance: usize,
+    pub min_gc: f64,
+    pub max_gc: f64,
+    pub max_homopolymer: usize,
+}
+
+fn longest_homopolymer(seq: &[u8]) -> usize {
+    seq.chunk_by(|a, b| a.eq_ignore_ascii_case(b))
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0)
+}
+
+fn gc_content(seq: &[u8]) -> Result<f64> {
+    Ok(compute_gc_content(vec![String::from_utf8_lossy(seq).to_string()])? as f64)
+}
+
+/// Whether a barcode on its own has an acceptable GC content and no long homopolymers.
+fn is_acceptable(seq: &[u8], opts: &BarcodeOptions) -> Result<bool> {
+    let gc = gc_content(seq)?;
+    Ok(gc >= opts.min_gc && gc <= opts.max_gc && longest_homopolymer(seq) <= opts.max_homopolymer)
+}
+
+fn distance(a: &[u8], b: &[u8], metric: Metric) -> Result<usize> {
+    let opts = DistanceOptions {
+        ignore_case: true,
+        ignore_strand: false,
+    };
+    distance::distance(metric, a, b, &opts)
+}
+
+/// Numbers of barcodes with signal in the red and green channels at each cycle, with Illumina
+/// two-color chemistry (as on the NextSeq and NovaSeq), where A is both red and green, C is red,
+/// T is green and G is dark. Every cycle needs signal in both channels for the instrument to
+/// register clusters.
+pub fn color_balance(barcodes: &[&[u8]]) -> Vec<(usize, usize)> {
+    let cycles = barcodes.iter().map(|b| b.len()).max().unwrap_or(0);
+    (0..cycles)
+        .map(|cycle| {
+            let bases = barcodes.iter().filter_map(|b| b.get(cycle));
+            bases.fold((0, 0), |(red, green), base| {
+                match base.to_ascii_uppercase() {
+                    b'A' => (red + 1, green + 1),
+                    b'C' => (red + 1, green),
+                    b'T' | b'U' => (red, green + 1),
+                    _ => (red, green),
+                }
+            })
+        })
+        .collect()
+}
+
+fn is_color_balanced(barcodes: &[&[u8]]) -> bool {
+    color_balance(barcodes)
+        .iter()
+        .all(|(red, green)| *red > 0 && *green > 0)
+}
+
+/// Randomly draws barcodes that meet the constraints and are far enough from every barcode
+/// chosen so far (including any existing ones), until there are enough of them. Sets that
+/// aren't color balanced are thrown away and started over.
+pub fn generate(
+    existing: &[&[u8]],
+    count: usize,
+    length: usize,
+    opts: &BarcodeOptions,
+    rng: &mut impl Rng,
+) -> Result<Vec<Vec<u8>>> {
+    let mut most_found = 0;
+    for _ in 0..MAX_RESTARTS {
+        let mut chosen: Vec<Vec<u8>> = vec![];
+        for _ in 0..MAX_ATTEMPTS {
+            if chosen.len() == count {
+                break;
+            }
+            let candidate: Vec<u8> = (0..length).map(|_| b"ACGT"[rng.gen_range(0..4)]).collect();
+            if !is_acceptable(&candidate, opts)? {
+                continue;
+            }
+            let mut far_enough = true;
+            for other in existing
+                .iter()
+                .copied()
+                .chain(chosen.iter().map(|c| c.as_slice()))
+            {
+                if distance(&candidate, other, opts.metric)? < opts.min_distance {
+                    far_enough = false;
+                    break;
+                }
+            }
+            if far_enough {
+                chosen.push(candidate);
+            }
+        }
+        most_found = most_found.max(chosen.len());
+        if chosen.len() < count {
+            break;
+        }
+        let set: Vec<&[u8]> = existing
+            .iter()
+            .copied()
+            .chain(chosen.iter().map(|c| c.as_slice()))
+            .collect();
+        if is_color_balanced(&set) {
+            return Ok(chosen);
+        }
+    }
+    if most_found < count {
+        bail!("Only found {most_found} of {count} barcodes that meet the constraints");
+    }
+    bail!("Unable to find a color balanced set of barcodes");
+}
+
+/// Reports the GC content, longest homopolymer and nearest neighbor of each barcode, the color
+/// balance at each cycle, and whether the set passes overall.
+pub fn validate(
+    barcodes: &[(String, Vec<u8>)],
+    opts: &BarcodeOptions,
+    with_ids: bool,
+) -> Result<String> {
+    let status = |passed: bool| if passed { "PASS" } else { "FAIL" };
+    let mut all_passed = true;
+    let header = "barcode\tgc\thomopolymer\tnearest\tdistance\tstatus";
+    let mut rows = vec![if with_ids {
+        format!("id\t{header}")
+    } else {
+        header.to_string()
+    }];
+    for (i, (id, seq)) in barcodes.iter().enumerate() {
+        let mut nearest: Option<(usize, usize)> = None;
+        for (j, (_, other)) in barcodes.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let d = distance(seq, other, opts.metric)?;
+            if nearest.is_none_or(|(_, best)| d < best) {
+                nearest = Some((j, d));
+            }
+        }
+        let passed =
+            is_acceptable(seq, opts)? && nearest.is_none_or(|(_, d)| d >= opts.min_distance);
+        all_passed &= passed;
+        let (nearest, d) = match nearest {
+            Some((j, d)) => (
+                String::from_utf8_lossy(&barcodes[j].1).to_string(),
+                d.to_string(),
+            ),
+            None => ("n/a".to_string(), "n/a".to_string()),
+        };
+        let row = format!(
+            "{}\t{:.4}\t{}\t{}\t{}\t{}",
+            String::from_utf8_lossy(seq),
+            gc_content(seq)?,
+            longest_homopolymer(seq),
+            nearest,
+            d,
+            status(passed)
+        );
+        rows.push(if with_ids {
+            format!("{id}\t{row}")
+        } else {
+            row
+        });
+    }
+    let seqs: Vec<&[u8]> = barcodes.iter().map(|(_, seq)| seq.as_slice()).collect();
+    let mut cycles = vec!["cycle\tred\tgreen\tstatus".to_string()];
+    for (cycle, (red, green)) in color_balance(&seqs).into_iter().enumerate() {
+        let passed = red > 0 && green > 0;
+        all_passed &= passed;
+        cycles.push(format!("{}\t{red}\t{green}\t{}", cycle + 1, status(passed)));
+    }
+    Ok(format!(
+        "{}\n\n{}\n\noverall\t{}",
+        rows.join("\n"),
+        cycles.join("\n"),
+        status(all_passed)
+    ))
+}
+
+pub fn barcodes(
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    opts: BarcodeOptions,
+    generate_count: Option<usize>,
+    length: usize,
+    seed: Option<u64>,
+) -> Result<String> {
+    if opts.min_gc > opts.max_gc {
+        bail!("Minimum GC content must not be greater than the maximum");
+    }
+    let barcodes: Vec<(String, Vec<u8>)> = match fasta {
+        Some(path) => fasta::read_records(path)?
+            .into_iter()
+            .map(|record| (record.id, record.seq.into_bytes()))
+            .collect(),
+        None => seqs
+            .into_iter()
+            .map(|seq| (String::new(), seq.replace("-", "").into_bytes()))
+            .collect(),
+    };
+    match generate_count {
+        Some(count) => {
+            if length == 0 {
+                bail!("Barcode length must be greater than zero");
+            }
+            let mut rng = match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            let existing: Vec<&[u8]> = barcodes.iter().map(|(_, seq)| seq.as_slice()).collect();
+            let generated = generate(&existing, count, length, &opts, &mut rng)?;
+            Ok(generated
+                .into_iter()
+                .map(|seq| String::from_utf8_lossy(&seq).to_string())
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        None => {
+            if barcodes.is_empty() {
+                bail!("No barcodes to validate");
+            }
+            validate(&barcodes, &opts, fasta.is_some())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn barcode_set(seqs: &[&str]) -> Vec<(String, Vec<u8>)> {
+        seqs.iter()
+            .map(|seq| (String::new(), seq.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_color_balance() {
+        // The first cycle is all G, so dark in both channels. The second has only C (red).
+        let balance = color_balance(&[b"GCA", b"GCT"]);
+        assert_eq!(balance, vec![(0, 0), (2, 0), (1, 2)]);
+        assert!(!is_color_balanced(&[b"GCA", b"GCT"]));
+        assert!(is_color_balanced(&[b"ACA", b"CTT"]));
+    }
+
+    #[test]
+    fn test_validate() {
+        let opts = BarcodeOptions {
+            metric: Metric::Hamming,
+            min_distance: 3,
+            min_gc: 0.25,
+            max_gc: 0.75,
+            max_homopolymer: 2,
+        };
+        let report = validate(&barcode_set(&["ACGTAC", "TGCATG", "ACGTAG"]), &opts, false).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[1], "ACGTAC\t0.5000\t1\tACGTAG\t1\tFAIL");
+        assert_eq!(lines[2], "TGCATG\t0.5000\t1\tACGTAG\t5\tPASS");
+        assert!(report.ends_with("overall\tFAIL"));
+        let report = validate(&barcode_set(&["ACACTA", "TAGAAC"]), &opts, false).unwrap();
+        assert!(report.ends_with("overall\tPASS"), "{report}");
+    }
+
+    #[test]
+    fn test_homopolymer_and_gc_fail() {
+        let opts = BarcodeOptions {
+            metric: Metric::Hamming,
+            min_distance: 3,
+            min_gc: 0.25,
+            max_gc: 0.75,
+            max_homopolymer: 2,
+        };
+        let report = validate(&barcode_set(&["AAATCG", "GCGCGG", "TGCATG"]), &opts, false).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[1].starts_with("AAATCG\t0.3333\t3\t"));
+        assert!(lines[1].ends_with("FAIL"));
+        assert!(lines[2].starts_with("GCGCGG\t1.0000\t2\t"));
+        assert!(lines[2].ends_with("FAIL"));
+    }
+
+    #[test]
+    fn test_generate() {
+        let opts = BarcodeOptions {
+            metric: Metric::Hamming,
+            min_distance: 3,
+            min_gc: 0.25,
+            max_gc: 0.75,
+            max_homopolymer: 2,
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let existing: Vec<&[u8]> = vec![b"ACGTACGT"];
+        let generated = generate(&existing, 12, 8, &opts, &mut rng).unwrap();
+        assert_eq!(generated.len(), 12);
+        let mut set: Vec<(String, Vec<u8>)> = barcode_set(&["ACGTACGT"]);
+        set.extend(generated.into_iter().map(|seq| (String::new(), seq)));
+        let report = validate(&set, &opts, false).unwrap();
+        assert!(report.ends_with("overall\tPASS"), "{report}");
+    }
+
+    #[test]
+    fn test_generate_impossible() {
+        let mut rng = StdRng::seed_from_u64(1);
+        // There are only 4 barcodes of length 1.
+        let opts = BarcodeOptions {
+            metric: Metric::Hamming,
+            min_distance: 1,
+            min_gc: 0.0,
+            max_gc: 1.0,
+            max_homopolymer: 2,
+        };
+        assert!(generate(&[], 5, 1, &opts, &mut rng).is_err());
+    }
+}
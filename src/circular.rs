@@ -0,0 +1,172 @@
+use crate::iupac;
+use anyhow::{bail, Result};
+use bio::alphabets::dna::revcomp;
+use std::cmp;
+
+/// Rotates a circular sequence so that it begins at `start` (zero-based).
+pub fn rotate(seq: &[u8], start: usize) -> Vec<u8> {
+    if seq.is_empty() {
+        return vec![];
+    }
+    let start = start % seq.len();
+    let mut rotated = seq[start..].to_vec();
+    rotated.extend_from_slice(&seq[..start]);
+    rotated
+}
+
+/// Appends up to `overlap` bases from the start of a circular sequence to its end, so that
+/// anything up to `overlap + 1` bases long that spans the origin can be found by scanning the
+/// result as if it were linear. Hits that start at or after the original length are duplicates
+/// of hits at the start of the sequence.
+pub fn extend_across_origin(seq: &[u8], overlap: usize) -> Vec<u8> {
+    let overlap = overlap.min(seq.len());
+    let mut extended = seq.to_vec();
+    extended.extend_from_slice(&seq[..overlap]);
+    extended
+}
+
+/// Converts a one-based origin given on the command line into a zero-based start position.
+pub fn origin_to_start(origin: usize, seq_len: usize) -> Result<usize> {
+    if origin == 0 || origin > seq_len {
+        bail!("Origin must be between 1 and the length of the sequence ({seq_len})");
+    }
+    Ok(origin - 1)
+}
+
+/// Finds the rotation of a sequence that is lexicographically smallest, using Booth's algorithm.
+pub fn least_rotation(seq: &[u8]) -> usize {
+    let n = seq.len();
+    let doubled: Vec<u8> = seq.iter().chain(seq.iter()).copied().collect();
+    let mut failure: Vec<isize> = vec![-1; 2 * n];
+    let mut k = 0;
+    for j in 1..2 * n {
+        let mut i = failure[j - k - 1];
+        while i != -1 && doubled[j] != doubled[k + i as usize + 1] {
+            if doubled[j] < doubled[k + i as usize + 1] {
+                k = j - i as usize - 1;
+            }
+            i = failure[i as usize];
+        }
+        if i == -1 && doubled[j] != doubled[k] {
+            if doubled[j] < doubled[k] {
+                k = j;
+            }
+            failure[j - k] = -1;
+        } else {
+            failure[j - k] = i + 1;
+        }
+    }
+    k
+}
+
+/// The lexicographically smallest rotation of either strand of a circular sequence. Two
+/// sequences that represent the same circular molecule will have the same canonical rotation,
+/// regardless of where their origins are or which strand they were given on.
+pub fn canonical_rotation(seq: &[u8]) -> Vec<u8> {
+    let seq = seq.to_ascii_uppercase();
+    let forward = rotate(&seq, least_rotation(&seq));
+    let rc = revcomp(&seq);
+    let reverse = rotate(&rc, least_rotation(&rc));
+    cmp::min(forward, reverse)
+}
+
+/// Finds the first position at which a (possibly degenerate) motif occurs in a circular sequence.
+pub fn find_motif(seq: &[u8], motif: &[u8]) -> Option<usize> {
+    if motif.is_empty() || motif.len() > seq.len() {
+        return None;
+    }
+    extend_across_origin(seq, motif.len() - 1)
+        .windows(motif.len())
+        .take(seq.len())
+        .position(|window| iupac::matches_at(motif, window))
+}
+
+pub fn rotate_sequence(
+    seqs: Vec<String>,
+    position: Option<usize>,
+    motif: Option<String>,
+    canonical: bool,
+) -> Result<String> {
+    let seq = seqs.join("").replace(" ", "").replace("-", "");
+    let seq = seq.as_bytes();
+    let rotated = if canonical {
+        canonical_rotation(seq)
+    } else if let Some(motif) = motif {
+        // If the motif isn't on the forward strand, we rotate the reverse complement instead so
+        // that the sequence still begins with the motif.
+        let motif = motif.as_bytes();
+        let rc = revcomp(seq);
+        match (find_motif(seq, motif), find_motif(&rc, motif)) {
+            (Some(start), _) => rotate(seq, start),
+            (None, Some(start)) => rotate(&rc, start),
+            (None, None) => bail!("Motif not found on either strand"),
+        }
+    } else if let Some(position) = position {
+        rotate(seq, origin_to_start(position, seq.len())?)
+    } else {
+        bail!("Give a position or motif to rotate to, or use --canonical");
+    };
+    Ok(String::from_utf8(rotated)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        assert_eq!(rotate(b"GATTACA", 3), b"TACAGAT");
+        assert_eq!(rotate(b"GATTACA", 0), b"GATTACA");
+        assert_eq!(rotate(b"GATTACA", 7), b"GATTACA");
+    }
+
+    #[test]
+    fn test_extend_across_origin() {
+        assert_eq!(extend_across_origin(b"GATTACA", 2), b"GATTACAGA");
+        assert_eq!(extend_across_origin(b"GAT", 10), b"GATGAT");
+    }
+
+    #[test]
+    fn test_origin_to_start() {
+        assert_eq!(origin_to_start(1, 10).unwrap(), 0);
+        assert!(origin_to_start(0, 10).is_err());
+        assert!(origin_to_start(11, 10).is_err());
+    }
+
+    #[test]
+    fn test_least_rotation() {
+        assert_eq!(least_rotation(b"CAB"), 1);
+        assert_eq!(least_rotation(b"TTGA"), 3);
+        assert_eq!(least_rotation(b"AAAA"), 0);
+        assert_eq!(least_rotation(b""), 0);
+    }
+
+    #[test]
+    fn test_canonical_rotation_ignores_origin_and_strand() {
+        let canonical = canonical_rotation(b"GGATTACAC");
+        assert_eq!(canonical, b"AATCCGTGT");
+        assert_eq!(canonical_rotation(b"TACACGGAT"), canonical);
+        assert_eq!(canonical_rotation(&revcomp(b"CACGGATTA")), canonical);
+    }
+
+    #[test]
+    fn test_rotate_to_motif() {
+        let seqs = vec!["ACAGAT".to_string(), "T".to_string()];
+        let rotated = rotate_sequence(seqs, None, Some("GATT".to_string()), false).unwrap();
+        assert_eq!(rotated, "GATTACA");
+    }
+
+    #[test]
+    fn test_rotate_to_motif_on_reverse_strand() {
+        let seqs = vec!["TGTAATC".to_string()];
+        let rotated = rotate_sequence(seqs, None, Some("GATT".to_string()), false).unwrap();
+        assert_eq!(rotated, "GATTACA");
+    }
+
+    #[test]
+    fn test_rotate_to_position() {
+        let seqs = vec!["GATTACA".to_string()];
+        let rotated = rotate_sequence(seqs, Some(4), None, false).unwrap();
+        assert_eq!(rotated, "TACAGAT");
+    }
+}
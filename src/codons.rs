@@ -0,0 +1,327 @@
+use crate::fasta;
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Codons are indexed in the conventional TCAG order: TTT, TTC, TTA, TTG, TCT, ..., GGG.
+const BASES: [u8; 4] = [b'T', b'C', b'A', b'G'];
+
+/// The standard genetic code, in TCAG order. Stop codons are '*'.
+const STANDARD_CODE: &[u8; 64] =
+    b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+
+/// Codon usage per thousand codons, from the Kazusa codon usage database.
+#[rustfmt::skip]
+const E_COLI: [f64; 64] = [
+    22.1, 16.0, 14.3, 13.0, 10.4, 9.1, 8.9, 8.5, 17.5, 12.2, 2.0, 0.3, 5.2, 6.1, 1.0, 13.9,
+    11.9, 10.2, 4.2, 48.4, 7.5, 5.4, 8.6, 20.9, 12.5, 9.3, 14.6, 28.4, 21.0, 22.0, 3.8, 5.9,
+    29.8, 23.7, 6.8, 26.4, 8.9, 22.8, 9.5, 13.7, 19.7, 20.3, 33.6, 10.3, 9.9, 15.2, 3.6, 2.1,
+    19.8, 14.3, 11.6, 25.9, 17.1, 24.2, 21.2, 30.1, 32.7, 19.2, 39.1, 18.7, 25.5, 27.1, 9.5, 11.3,
+];
+
+#[rustfmt::skip]
+const HUMAN: [f64; 64] = [
+    17.6, 20.3, 7.7, 12.9, 15.2, 17.7, 12.2, 4.4, 12.2, 15.3, 1.0, 0.8, 10.6, 12.6, 1.6, 13.2,
+    13.2, 19.6, 7.2, 39.6, 17.5, 19.8, 16.9, 6.9, 10.9, 15.1, 12.3, 34.2, 4.5, 10.4, 6.2, 11.4,
+    16.0, 20.8, 7.5, 22.0, 13.1, 18.9, 15.1, 6.1, 17.0, 19.1, 24.4, 31.9, 12.1, 19.5, 12.2, 12.0,
+    11.0, 14.5, 7.1, 28.1, 18.4, 27.7, 15.8, 7.4, 21.8, 25.1, 29.0, 39.6, 10.8, 22.2, 16.5, 16.5,
+];
+
+#[rustfmt::skip]
+const YEAST: [f64; 64] = [
+    26.1, 18.4, 26.2, 27.2, 23.5, 14.2, 18.7, 8.6, 18.8, 14.8, 1.1, 0.5, 8.1, 4.8, 0.7, 10.4,
+    12.3, 5.4, 13.4, 10.5, 13.5, 6.8, 18.3, 5.3, 13.6, 7.8, 27.3, 12.1, 6.4, 2.6, 3.0, 1.7,
+    30.1, 17.2, 17.8, 20.9, 20.3, 12.7, 17.8, 8.0, 35.7, 24.8, 41.9, 30.8, 14.2, 9.8, 21.3, 9.2,
+    22.1, 11.8, 11.8, 10.8, 21.2, 12.6, 16.2, 6.2, 37.6, 20.2, 45.6, 19.2, 23.9, 9.8, 10.9, 6.0,
+];
+
+/// Weight given to codons that never occur in the reference, so that a single rare codon
+/// doesn't make the CAI zero.
+const MIN_WEIGHT: f64 = 0.01;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Organism {
+    /// Escherichia coli K-12
+    Ecoli,
+    /// Homo sapiens
+    Human,
+    /// Saccharomyces cerevisiae
+    Yeast,
+}
+
+pub fn codon_index(codon: &[u8]) -> Option<usize> {
+    let mut index = 0;
+    for base in codon {
+        let base = match base.to_ascii_uppercase() {
+            b'U' => b'T',
+            base => base,
+        };
+        index = index * 4 + BASES.iter().position(|b| *b == base)?;
+    }
+    Some(index)
+}
+
+pub fn codon_at(index: usize) -> String {
+    [index / 16, index / 4 % 4, index % 4]
+        .iter()
+        .map(|i| BASES[*i] as char)
+        .collect()
+}
+
+pub fn amino_acid(index: usize) -> u8 {
+    STANDARD_CODE[index]
+}
+
+/// Indexes of every codon for an amino acid (or '*' for stop codons).
+pub fn synonymous_codons(amino_acid: u8) -> Vec<usize> {
+    (0..64)
+        .filter(|i| STANDARD_CODE[*i] == amino_acid)
+        .collect()
+}
+
+pub struct CodonTable {
+    /// Relative usage of each codon. Only ratios between synonymous codons matter, so these may
+    /// be counts, fractions or frequencies per thousand.
+    pub usage: [f64; 64],
+}
+
+impl CodonTable {
+    pub fn for_organism(organism: Organism) -> CodonTable {
+        let usage = match organism {
+            Organism::Ecoli => E_COLI,
+            Organism::Human => HUMAN,
+            Organism::Yeast => YEAST,
+        };
+        CodonTable { usage }
+    }
+
+    /// Reads a codon usage table from a file. Each codon should be followed by its usage, and
+    /// anything else is ignored, so both simple two-column tables and the Kazusa format
+    /// ("UUU 17.6(714298) UCU 15.2(618711) ...") can be used. Missing codons are unused.
+    pub fn from_file(path: &Path) -> Result<CodonTable> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read codon usage table {}", path.display()))?;
+        CodonTable::parse(&text)
+            .with_context(|| format!("Invalid codon usage table {}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<CodonTable> {
+        let tokens: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .collect();
+        let mut usage = [0.0; 64];
+        let mut found = false;
+        for pair in tokens.windows(2) {
+            let Some(index) = codon_index(pair[0].as_bytes()).filter(|_| pair[0].len() == 3) else {
+                continue;
+            };
+            let value = pair[1].split('(').next().unwrap_or_default();
+            if let Ok(value) = value.parse::<f64>() {
+                if value < 0.0 {
+                    bail!("Negative usage for codon {}", pair[0]);
+                }
+                usage[index] = value;
+                found = true;
+            }
+        }
+        if !found {
+            bail!("No codons found");
+        }
+        Ok(CodonTable { usage })
+    }
+
+    /// The relative adaptiveness of each codon (Sharp & Li, 1987): its usage divided by that
+    /// of the most used codon for the same amino acid.
+    pub fn weights(&self) -> [f64; 64] {
+        let mut weights = [0.0; 64];
+        for (index, weight) in weights.iter_mut().enumerate() {
+            let max = synonymous_codons(amino_acid(index))
+                .iter()
+                .map(|i| self.usage[*i])
+                .fold(0.0, f64::max);
+            if max > 0.0 {
+                *weight = self.usage[index] / max;
+            }
+        }
+        weights
+    }
+}
+
+/// Counts of each codon in a coding sequence, which must be a whole number of unambiguous
+/// codons.
+pub fn count_codons(seq: &str) -> Result<[usize; 64]> {
+    if !seq.len().is_multiple_of(3) {
+        bail!(
+            "Coding sequence length ({}) is not a multiple of 3",
+            seq.len()
+        );
+    }
+    let mut counts = [0; 64];
+    for (i, codon) in seq.as_bytes().chunks(3).enumerate() {
+        match codon_index(codon) {
+            Some(index) => counts[index] += 1,
+            None => bail!(
+                "Invalid/ambiguous codon: '{}' at position {}",
+                String::from_utf8_lossy(codon),
+                i * 3
+            ),
+        }
+    }
+    Ok(counts)
+}
+
+/// The Codon Adaptation Index (Sharp & Li, 1987): the geometric mean of the weights of each
+/// codon. Methionine, tryptophan and stop codons are excluded since they have no synonyms.
+/// Returns `None` if there are no informative codons.
+pub fn codon_adaptation_index(counts: &[usize; 64], weights: &[f64; 64]) -> Option<f64> {
+    let mut log_sum = 0.0;
+    let mut total = 0;
+    for (index, count) in counts.iter().enumerate() {
+        if *count == 0
+            || synonymous_codons(amino_acid(index)).len() < 2
+            || amino_acid(index) == b'*'
+        {
+            continue;
+        }
+        log_sum += *count as f64 * weights[index].max(MIN_WEIGHT).ln();
+        total += count;
+    }
+    if total == 0 {
+        None
+    } else {
+        Some((log_sum / total as f64).exp())
+    }
+}
+
+fn format_codon_usage(counts: &[usize; 64], weights: &[f64; 64]) -> String {
+    let total: usize = counts.iter().sum();
+    let cai = codon_adaptation_index(counts, weights);
+    let mut output = vec![
+        format!("codons\t{total}"),
+        format!("cai\t{}", crate::composition::format_optional(cai)),
+        "codon\tamino_acid\tcount\tfraction\tper_thousand\tweight".to_string(),
+    ];
+    for (index, count) in counts.iter().enumerate() {
+        let synonymous: usize = synonymous_codons(amino_acid(index))
+            .iter()
+            .map(|i| counts[*i])
+            .sum();
+        let fraction = if synonymous == 0 {
+            0.0
+        } else {
+            *count as f64 / synonymous as f64
+        };
+        let per_thousand = if total == 0 {
+            0.0
+        } else {
+            1000.0 * *count as f64 / total as f64
+        };
+        output.push(format!(
+            "{}\t{}\t{}\t{:.4}\t{:.1}\t{:.4}",
+            codon_at(index),
+            amino_acid(index) as char,
+            count,
+            fraction,
+            per_thousand,
+            weights[index]
+        ));
+    }
+    output.join("\n")
+}
+
+pub fn codon_usage(
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    organism: Organism,
+    table: Option<&Path>,
+) -> Result<String> {
+    let table = match table {
+        Some(path) => CodonTable::from_file(path)?,
+        None => CodonTable::for_organism(organism),
+    };
+    let weights = table.weights();
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let mut output = vec![];
+    for record in records {
+        let counts = count_codons(&record.seq)?;
+        let usage = format_codon_usage(&counts, &weights);
+        if record.id.is_empty() {
+            output.push(usage);
+        } else {
+            output.push(format!(">{}\n{}", record.id, usage));
+        }
+    }
+    Ok(output.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codon_index() {
+        assert_eq!(codon_index(b"TTT"), Some(0));
+        assert_eq!(codon_index(b"ggg"), Some(63));
+        assert_eq!(codon_index(b"AUG"), Some(35));
+        assert_eq!(codon_at(35), "ATG");
+        assert_eq!(amino_acid(35), b'M');
+        assert_eq!(codon_index(b"ANG"), None);
+    }
+
+    #[test]
+    fn test_genetic_code() {
+        assert_eq!(synonymous_codons(b'*').len(), 3);
+        assert_eq!(synonymous_codons(b'L').len(), 6);
+        assert_eq!(synonymous_codons(b'W'), vec![codon_index(b"TGG").unwrap()]);
+    }
+
+    #[test]
+    fn test_count_codons_rejects_partial_codons() {
+        assert!(count_codons("ATGAA").is_err());
+        assert!(count_codons("ATGNNN").is_err());
+    }
+
+    #[test]
+    fn test_cai_of_preferred_codons_is_one() {
+        let weights = CodonTable::for_organism(Organism::Ecoli).weights();
+        // CTG (Leu), GAA (Glu) and AAA (Lys) are the most used E. coli codons for their amino
+        // acids; ATG and the stop codon are ignored.
+        let counts = count_codons("ATGCTGGAAAAATAA").unwrap();
+        assert!((codon_adaptation_index(&counts, &weights).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cai_of_rare_codons() {
+        let weights = CodonTable::for_organism(Organism::Ecoli).weights();
+        // AGG is the rarest E. coli arginine codon: 2.1 / 22.0
+        let counts = count_codons("AGG").unwrap();
+        let cai = codon_adaptation_index(&counts, &weights).unwrap();
+        assert!((cai - 2.1 / 22.0).abs() < 1e-9);
+        assert_eq!(
+            codon_adaptation_index(&count_codons("ATGTGG").unwrap(), &weights),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_kazusa_table() {
+        let table =
+            CodonTable::parse("UUU 17.6(714298)  UCU 15.2(618711)\nUUC 20.3(824692)").unwrap();
+        assert_eq!(table.usage[0], 17.6);
+        assert_eq!(table.usage[1], 20.3);
+        assert_eq!(table.usage[4], 15.2);
+        assert_eq!(table.usage[63], 0.0);
+        assert!(CodonTable::parse("codon\tusage").is_err());
+    }
+
+    #[test]
+    fn test_codon_usage_output() {
+        let actual =
+            codon_usage(vec!["ATGTTTTTC".to_string()], None, Organism::Ecoli, None).unwrap();
+        let lines: Vec<&str> = actual.lines().collect();
+        assert_eq!(lines[0], "codons\t3");
+        assert_eq!(lines[3], "TTT\tF\t1\t0.5000\t333.3\t1.0000");
+        assert_eq!(lines[4], "TTC\tF\t1\t0.5000\t333.3\t0.7240");
+    }
+}
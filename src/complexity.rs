@@ -0,0 +1,240 @@
+use crate::fasta;
+use crate::iupac;
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum MaskStyle {
+    /// Convert low-complexity bases to lowercase (soft-masking)
+    Lowercase,
+    /// Replace low-complexity bases with N (hard-masking)
+    N,
+}
+
+fn base_index(base: u8) -> Option<usize> {
+    match base.to_ascii_uppercase() {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' | b'U' => Some(3),
+        _ => None,
+    }
+}
+
+/// Shannon entropy (in bits) of the base composition of a sequence. Ranges from 0 for a
+/// homopolymer to 2 for a sequence with equal amounts of each base. Ambiguous bases are ignored.
+pub fn shannon_entropy(seq: &[u8]) -> f64 {
+    let mut counts = [0usize; 4];
+    for base in seq {
+        if let Some(i) = base_index(*base) {
+            counts[i] += 1;
+        }
+    }
+    let total: usize = counts.iter().sum();
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// The DUST score of a sequence (Morgulis et al., 2006), scaled by 10 as in dustmasker and
+/// sdust, so that the conventional masking threshold is 20. Each distinct triplet contributes
+/// c(c - 1) / 2, where c is the number of times it occurs, and the total is normalized by the
+/// number of triplets minus one. Triplets with ambiguous bases are ignored.
+pub fn dust_score(seq: &[u8]) -> f64 {
+    if seq.len() < 4 {
+        return 0.0;
+    }
+    let mut counts = [0usize; 64];
+    for triplet in seq.windows(3) {
+        if let Some(i) = triplet_index(triplet) {
+            counts[i] += 1;
+        }
+    }
+    let repeats: usize = counts.iter().map(|c| c * c.saturating_sub(1) / 2).sum();
+    let triplets = seq.len() - 2;
+    10.0 * repeats as f64 / (triplets - 1) as f64
+}
+
+/// Start positions of each window. Sequences shorter than the window are treated as a single
+/// window, so that short sequences such as guides and probes can still be evaluated.
+fn window_starts(len: usize, window: usize, step: usize) -> Vec<usize> {
+    if len <= window {
+        vec![0]
+    } else {
+        (0..=len - window).step_by(step).collect()
+    }
+}
+
+pub struct Thresholds {
+    pub dust: f64,
+    pub min_entropy: Option<f64>,
+}
+
+impl Thresholds {
+    fn is_low_complexity(&self, window: &[u8]) -> bool {
+        dust_score(window) > self.dust
+            || self
+                .min_entropy
+                .is_some_and(|min| shannon_entropy(window) < min)
+    }
+}
+
+fn triplet_index(triplet: &[u8]) -> Option<usize> {
+    Some(base_index(triplet[0])? * 16 + base_index(triplet[1])? * 4 + base_index(triplet[2])?)
+}
+
+/// Masks low-complexity regions. Masking entire windows would also mask the unique sequence
+/// that flanks a short repeat, so within each low-complexity window only the bases that are part
+/// of a triplet that occurs more than once in that window are masked.
+pub fn mask(seq: &[u8], window: usize, thresholds: &Thresholds, style: MaskStyle) -> Vec<u8> {
+    let mut is_masked = vec![false; seq.len()];
+    for start in window_starts(seq.len(), window, 1) {
+        let end = (start + window).min(seq.len());
+        let window = &seq[start..end];
+        if !thresholds.is_low_complexity(window) {
+            continue;
+        }
+        let mut counts = [0usize; 64];
+        for triplet in window.windows(3) {
+            if let Some(i) = triplet_index(triplet) {
+                counts[i] += 1;
+            }
+        }
+        for (offset, triplet) in window.windows(3).enumerate() {
+            if triplet_index(triplet).is_some_and(|i| counts[i] > 1) {
+                let position = start + offset;
+                is_masked[position..position + 3].fill(true);
+            }
+        }
+    }
+    seq.iter()
+        .zip(is_masked)
+        .map(|(base, is_masked)| match (is_masked, style) {
+            (false, _) => *base,
+            (true, MaskStyle::Lowercase) => base.to_ascii_lowercase(),
+            (true, MaskStyle::N) => b'N',
+        })
+        .collect()
+}
+
+pub struct ComplexityOptions {
+    pub window: usize,
+    pub step: usize,
+    pub thresholds: Thresholds,
+    pub mask: Option<MaskStyle>,
+}
+
+pub fn complexity(
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    opts: ComplexityOptions,
+) -> Result<String> {
+    if opts.window < 4 || opts.step == 0 {
+        bail!("Window size must be at least 4 and step must be greater than zero");
+    }
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let mut output = vec![];
+    for record in records {
+        iupac::confirm_valid_iupac(&record.seq)?;
+        let seq = record.seq.as_bytes();
+        if let Some(style) = opts.mask {
+            let masked = String::from_utf8(mask(seq, opts.window, &opts.thresholds, style))?;
+            if record.id.is_empty() {
+                output.push(masked);
+            } else {
+                output.push(format!(">{}\n{}", record.id, masked));
+            }
+            continue;
+        }
+        // A BED-like track, with the record ID as the first column when reading FASTA.
+        for start in window_starts(seq.len(), opts.window, opts.step) {
+            let end = (start + opts.window).min(seq.len());
+            let window = &seq[start..end];
+            let line = format!(
+                "{}\t{}\t{:.4}\t{:.4}",
+                start,
+                end,
+                shannon_entropy(window),
+                dust_score(window)
+            );
+            if record.id.is_empty() {
+                output.push(line);
+            } else {
+                output.push(format!("{}\t{}", record.id, line));
+            }
+        }
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shannon_entropy() {
+        assert_eq!(shannon_entropy(b"AAAA"), 0.0);
+        assert_eq!(shannon_entropy(b"ACGT"), 2.0);
+        assert_eq!(shannon_entropy(b"AATT"), 1.0);
+    }
+
+    #[test]
+    fn test_dust_score() {
+        // A homopolymer of length 10 has 8 identical triplets: 10 * (8 * 7 / 2) / 7
+        assert_eq!(dust_score(b"AAAAAAAAAA"), 40.0);
+        // No triplet occurs twice.
+        assert_eq!(dust_score(b"ACGTTGCA"), 0.0);
+    }
+
+    #[test]
+    fn test_mask_lowercase() {
+        let seq = b"ACGTTGCAAGCTAGCATCGAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACGTCTGAGTTCC";
+        let thresholds = Thresholds {
+            dust: 20.0,
+            min_entropy: None,
+        };
+        let masked = mask(seq, 20, &thresholds, MaskStyle::Lowercase);
+        let masked = String::from_utf8(masked).unwrap();
+        assert_eq!(
+            masked,
+            "ACGTTGCAAGCTAGCATCGaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaCGTCTGAGTTCC"
+        );
+    }
+
+    #[test]
+    fn test_mask_short_sequence_by_entropy() {
+        let thresholds = Thresholds {
+            dust: 1000.0,
+            min_entropy: Some(1.5),
+        };
+        assert_eq!(
+            mask(b"ATATATAT", 64, &thresholds, MaskStyle::N),
+            b"NNNNNNNN"
+        );
+        assert_eq!(
+            mask(b"ACGTACGT", 64, &thresholds, MaskStyle::N),
+            b"ACGTACGT"
+        );
+    }
+
+    #[test]
+    fn test_complexity_track() {
+        let opts = ComplexityOptions {
+            window: 4,
+            step: 4,
+            thresholds: Thresholds {
+                dust: 20.0,
+                min_entropy: None,
+            },
+            mask: None,
+        };
+        let track = complexity(vec!["ACGTAAAA".to_string()], None, opts).unwrap();
+        assert_eq!(track, "0\t4\t2.0000\t0.0000\n4\t8\t0.0000\t10.0000");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::fasta;
+use crate::iupac;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const NUCLEOTIDES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+pub struct Composition {
+    pub length: usize,
+    /// Counts of each (uppercased) base, including ambiguous bases.
+    pub counts: BTreeMap<char, usize>,
+    /// Counts of each dinucleotide of unambiguous bases, indexed in the order of `NUCLEOTIDES`.
+    pub dinucleotides: [[usize; 4]; 4],
+}
+
+fn nucleotide_index(base: char) -> Option<usize> {
+    match base {
+        'A' => Some(0),
+        'C' => Some(1),
+        'G' => Some(2),
+        'T' | 'U' => Some(3),
+        _ => None,
+    }
+}
+
+impl Composition {
+    pub fn new(seq: &str) -> Result<Composition> {
+        iupac::confirm_valid_iupac(seq)?;
+        let seq = seq.to_ascii_uppercase();
+        let mut counts = BTreeMap::new();
+        for base in seq.chars() {
+            *counts.entry(base).or_insert(0) += 1;
+        }
+        let mut dinucleotides = [[0; 4]; 4];
+        let bases: Vec<char> = seq.chars().collect();
+        for pair in bases.windows(2) {
+            if let (Some(i), Some(j)) = (nucleotide_index(pair[0]), nucleotide_index(pair[1])) {
+                dinucleotides[i][j] += 1;
+            }
+        }
+        Ok(Composition {
+            length: bases.len(),
+            counts,
+            dinucleotides,
+        })
+    }
+
+    fn count(&self, base: char) -> usize {
+        self.counts.get(&base).copied().unwrap_or(0)
+    }
+
+    /// Thymine and uracil are counted together, so RNA gets the same statistics as DNA.
+    fn count_t(&self) -> usize {
+        self.count('T') + self.count('U')
+    }
+
+    /// (G - C) / (G + C), or `None` if there are no Gs or Cs.
+    pub fn gc_skew(&self) -> Option<f64> {
+        skew(self.count('G'), self.count('C'))
+    }
+
+    /// (A - T) / (A + T), or `None` if there are no As or Ts.
+    pub fn at_skew(&self) -> Option<f64> {
+        skew(self.count('A'), self.count_t())
+    }
+
+    /// The number of CpG dinucleotides relative to the number expected from the C and G
+    /// content (Gardiner-Garden & Frommer, 1987), or `None` if there are no Cs or Gs.
+    pub fn cpg_observed_expected(&self) -> Option<f64> {
+        let expected = self.count('C') * self.count('G');
+        if expected == 0 {
+            return None;
+        }
+        let observed = self.dinucleotides[1][2];
+        Some((observed * self.length) as f64 / expected as f64)
+    }
+}
+
+pub fn skew(a: usize, b: usize) -> Option<f64> {
+    if a + b == 0 {
+        None
+    } else {
+        Some((a as f64 - b as f64) / (a + b) as f64)
+    }
+}
+
+pub fn format_optional(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.4}", value),
+        None => "n/a".to_string(),
+    }
+}
+
+fn fraction(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+fn format_composition(composition: &Composition) -> String {
+    let mut output = vec![format!("length\t{}", composition.length)];
+
+    output.push("base\tcount\tfraction".to_string());
+    let mut bases = NUCLEOTIDES.to_vec();
+    bases.extend(
+        composition
+            .counts
+            .keys()
+            .filter(|base| !NUCLEOTIDES.contains(base)),
+    );
+    for base in bases {
+        let count = composition.count(base);
+        output.push(format!(
+            "{}\t{}\t{:.4}",
+            base,
+            count,
+            fraction(count, composition.length)
+        ));
+    }
+
+    output.push(format!(
+        "gc_skew\t{}",
+        format_optional(composition.gc_skew())
+    ));
+    output.push(format!(
+        "at_skew\t{}",
+        format_optional(composition.at_skew())
+    ));
+    output.push(format!(
+        "cpg_o/e\t{}",
+        format_optional(composition.cpg_observed_expected())
+    ));
+
+    output.push("dinucleotide\tcount\tfrequency".to_string());
+    let total: usize = composition.dinucleotides.iter().flatten().sum();
+    for (i, first) in NUCLEOTIDES.iter().enumerate() {
+        for (j, second) in NUCLEOTIDES.iter().enumerate() {
+            let count = composition.dinucleotides[i][j];
+            output.push(format!(
+                "{}{}\t{}\t{:.4}",
+                first,
+                second,
+                count,
+                fraction(count, total)
+            ));
+        }
+    }
+    output.join("\n")
+}
+
+pub fn composition(seqs: Vec<String>, fasta: Option<&Path>) -> Result<String> {
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let mut output = vec![];
+    for record in records {
+        let composition = Composition::new(&record.seq)?;
+        if record.id.is_empty() {
+            output.push(format_composition(&composition));
+        } else {
+            output.push(format!(
+                ">{}\n{}",
+                record.id,
+                format_composition(&composition)
+            ));
+        }
+    }
+    Ok(output.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_include_ambiguous_bases() {
+        let composition = Composition::new("ACGTNNRa").unwrap();
+        assert_eq!(composition.length, 8);
+        assert_eq!(composition.count('A'), 2);
+        assert_eq!(composition.count('N'), 2);
+        assert_eq!(composition.count('R'), 1);
+    }
+
+    #[test]
+    fn test_dinucleotides_skip_ambiguous_bases() {
+        let composition = Composition::new("CGNCG").unwrap();
+        assert_eq!(composition.dinucleotides[1][2], 2);
+        assert_eq!(composition.dinucleotides.iter().flatten().sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn test_skews() {
+        let composition = Composition::new("GGGCAAAT").unwrap();
+        assert_eq!(composition.gc_skew(), Some(0.5));
+        assert_eq!(composition.at_skew(), Some(0.5));
+        let composition = Composition::new("AAAA").unwrap();
+        assert_eq!(composition.gc_skew(), None);
+    }
+
+    #[test]
+    fn test_cpg_observed_expected() {
+        // 2 CpGs in 8 bases with 2 Cs and 2 Gs: (2 * 8) / (2 * 2)
+        let composition = Composition::new("CGAACGAA").unwrap();
+        assert_eq!(composition.cpg_observed_expected(), Some(4.0));
+    }
+
+    #[test]
+    fn test_rna_uracil_counts_as_thymine_in_statistics() {
+        let composition = Composition::new("AAUU").unwrap();
+        assert_eq!(composition.at_skew(), Some(0.0));
+        assert_eq!(composition.dinucleotides[0][3], 1);
+    }
+
+    #[test]
+    fn test_composition_output() {
+        let actual = composition(vec!["GA".to_string(), "T-N".to_string()], None).unwrap();
+        let lines: Vec<&str> = actual.lines().collect();
+        assert_eq!(lines[0], "length\t4");
+        assert_eq!(lines[2], "A\t1\t0.2500");
+        assert_eq!(lines[6], "N\t1\t0.2500");
+        assert_eq!(lines[7], "gc_skew\t1.0000");
+        assert_eq!(lines[9], "cpg_o/e\tn/a");
+    }
+}
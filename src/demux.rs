@@ -0,0 +1,318 @@
+use crate::composition::format_optional;
+use crate::fasta;
+use anyhow::{bail, Context, Result};
+use bio::io::fastq;
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::Path;
+
+const UNASSIGNED: &str = "unassigned";
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum BarcodeLocation {
+    /// At the start of each read
+    Inline,
+    /// In the index field at the end of the read header (e.g. 1:N:0:ACGTACGT+TTGACCAA)
+    Index,
+}
+
+pub struct DemuxOptions {
+    pub location: BarcodeLocation,
+    pub max_mismatches: usize,
+    pub keep_barcode: bool,
+}
+
+fn count_mismatches(barcode: &[u8], observed: &[u8]) -> usize {
+    barcode
+        .iter()
+        .zip(observed)
+        .filter(|(a, b)| !a.eq_ignore_ascii_case(b))
+        .count()
+}
+
+/// The barcode an observed sequence is closest to, if it's within the allowed mismatches and no
+/// other barcode is just as close. Inline barcodes are compared to the start of the read.
+pub fn assign(
+    observed: &[u8],
+    barcodes: &[Vec<u8>],
+    max_mismatches: usize,
+    location: BarcodeLocation,
+) -> Option<usize> {
+    let mut best: Option<(usize, usize)> = None;
+    let mut tied = false;
+    for (i, barcode) in barcodes.iter().enumerate() {
+        let comparable = match location {
+            BarcodeLocation::Inline => observed.len() >= barcode.len(),
+            BarcodeLocation::Index => observed.len() == barcode.len(),
+        };
+        if !comparable {
+            continue;
+        }
+        let mismatches = count_mismatches(barcode, observed);
+        if mismatches > max_mismatches {
+            continue;
+        }
+        match best {
+            Some((_, fewest)) if mismatches == fewest => tied = true,
+            Some((_, fewest)) if mismatches > fewest => {}
+            _ => {
+                best = Some((i, mismatches));
+                tied = false;
+            }
+        }
+    }
+    if tied {
+        None
+    } else {
+        best.map(|(i, _)| i)
+    }
+}
+
+/// The index sequence at the end of an Illumina read header, after the last colon.
+fn read_index(record: &fastq::Record) -> Result<&[u8]> {
+    match record.desc().and_then(|desc| desc.rsplit(':').next()) {
+        Some(index) if !index.is_empty() => Ok(index.as_bytes()),
+        _ => bail!("Read {} has no index in its header", record.id()),
+    }
+}
+
+/// Sample names become file names, so they mustn't be able to point outside of the output
+/// directory.
+fn confirm_valid_sample_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        bail!("Sample names must be non-empty and not contain '/', '\\' or '..': '{name}'");
+    }
+    Ok(())
+}
+
+fn create_writer(out_dir: &Path, name: &str) -> Result<fastq::Writer<File>> {
+    let path = out_dir.join(format!("{name}.fastq"));
+    fastq::Writer::to_file(&path).with_context(|| format!("Failed to create {}", path.display()))
+}
+
+/// Splits the reads in a FASTQ file into one file per sample (and one of unassigned reads) in
+/// the output directory, and reports how many reads went to each.
+pub fn demux(
+    reads: &Path,
+    barcode_file: &Path,
+    out_dir: &Path,
+    opts: DemuxOptions,
+) -> Result<String> {
+    if opts.keep_barcode && opts.location != BarcodeLocation::Inline {
+        bail!("--keep-barcode only applies to inline barcodes");
+    }
+    let samples = fasta::read_records(barcode_file)?;
+    if samples.is_empty() {
+        bail!("No barcodes in {}", barcode_file.display());
+    }
+    let mut names = HashSet::new();
+    for sample in &samples {
+        confirm_valid_sample_name(&sample.id)?;
+        if sample.seq.is_empty() {
+            bail!("Sample {} has an empty barcode", sample.id);
+        }
+        if sample.id == UNASSIGNED || !names.insert(sample.id.as_str()) {
+            bail!(
+                "Sample names must be unique and not '{UNASSIGNED}': {}",
+                sample.id
+            );
+        }
+    }
+    let barcodes: Vec<Vec<u8>> = samples.iter().map(|s| s.seq.clone().into_bytes()).collect();
+
+    let reader = fastq::Reader::from_file(reads)
+        .with_context(|| format!("Failed to open FASTQ file {}", reads.display()))?;
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create directory {}", out_dir.display()))?;
+    let mut writers = samples
+        .iter()
+        .map(|sample| create_writer(out_dir, &sample.id))
+        .collect::<Result<Vec<_>>>()?;
+    let mut unassigned_writer = create_writer(out_dir, UNASSIGNED)?;
+    let mut counts = vec![0usize; samples.len()];
+    let mut unassigned = 0;
+
+    for record in reader.records() {
+        let record = record.context("Failed to parse FASTQ record")?;
+        let observed = match opts.location {
+            BarcodeLocation::Inline => record.seq(),
+            BarcodeLocation::Index => read_index(&record)?,
+        };
+        match assign(observed, &barcodes, opts.max_mismatches, opts.location) {
+            Some(i) => {
+                counts[i] += 1;
+                if opts.location == BarcodeLocation::Inline && !opts.keep_barcode {
+                    let trimmed = fastq::Record::with_attrs(
+                        record.id(),
+                        record.desc(),
+                        &record.seq()[barcodes[i].len()..],
+                        &record.qual()[barcodes[i].len()..],
+                    );
+                    writers[i].write_record(&trimmed)?;
+                } else {
+                    writers[i].write_record(&record)?;
+                }
+            }
+            None => {
+                unassigned += 1;
+                unassigned_writer.write_record(&record)?;
+            }
+        }
+    }
+    for writer in writers.iter_mut().chain([&mut unassigned_writer]) {
+        writer.flush()?;
+    }
+
+    let total = counts.iter().sum::<usize>() + unassigned;
+    let fraction = |count: usize| {
+        format_optional(if total == 0 {
+            None
+        } else {
+            Some(count as f64 / total as f64)
+        })
+    };
+    let mut output = vec!["sample\tbarcode\treads\tfraction".to_string()];
+    for (sample, count) in samples.iter().zip(&counts) {
+        output.push(format!(
+            "{}\t{}\t{}\t{}",
+            sample.id,
+            sample.seq,
+            count,
+            fraction(*count)
+        ));
+    }
+    output.push(format!(
+        "{UNASSIGNED}\tn/a\t{unassigned}\t{}",
+        fraction(unassigned)
+    ));
+    output.push(format!("total\tn/a\t{total}\t{}", fraction(total)));
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn barcodes(seqs: &[&str]) -> Vec<Vec<u8>> {
+        seqs.iter().map(|s| s.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_assign_inline() {
+        let barcodes = barcodes(&["ACGT", "TTGCA"]);
+        let inline = BarcodeLocation::Inline;
+        assert_eq!(assign(b"ACGTGGGG", &barcodes, 0, inline), Some(0));
+        assert_eq!(assign(b"TTGCAGGG", &barcodes, 0, inline), Some(1));
+        assert_eq!(assign(b"ACCTGGGG", &barcodes, 0, inline), None);
+        assert_eq!(assign(b"ACCTGGGG", &barcodes, 1, inline), Some(0));
+        assert_eq!(assign(b"acgt", &barcodes, 0, inline), Some(0));
+        assert_eq!(assign(b"ACG", &barcodes, 1, inline), None);
+    }
+
+    #[test]
+    fn test_ties_are_unassigned() {
+        let barcodes = barcodes(&["AAAA", "AATT"]);
+        let index = BarcodeLocation::Index;
+        // AAAT is one mismatch from both.
+        assert_eq!(assign(b"AAAT", &barcodes, 1, index), None);
+        assert_eq!(assign(b"AAAA", &barcodes, 2, index), Some(0));
+    }
+
+    #[test]
+    fn test_index_must_match_length() {
+        let barcodes = barcodes(&["ACGT+TTGA"]);
+        let index = BarcodeLocation::Index;
+        assert_eq!(assign(b"ACGT+TTGA", &barcodes, 0, index), Some(0));
+        assert_eq!(assign(b"ACGT+TTGAC", &barcodes, 0, index), None);
+        assert_eq!(assign(b"ACGA+TTGA", &barcodes, 1, index), Some(0));
+    }
+
+    #[test]
+    fn test_sample_names_must_stay_in_out_dir() {
+        assert!(confirm_valid_sample_name("s1").is_ok());
+        assert!(confirm_valid_sample_name("../escaped").is_err());
+        assert!(confirm_valid_sample_name("a/b").is_err());
+        assert!(confirm_valid_sample_name("..").is_err());
+        assert!(confirm_valid_sample_name("").is_err());
+    }
+
+    #[test]
+    fn test_demux() {
+        let dir = std::env::temp_dir().join(format!("biotools-demux-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let reads = dir.join("reads.fastq");
+        let barcodes = dir.join("barcodes.fa");
+        let out_dir = dir.join("out");
+        fs::write(
+            &reads,
+            "@r1\nACGTGGGG\n+\nIIIIIIII\n@r2\nTTGCAGGG\n+\nIIIIIIII\n\
+             @r3\nACCTCCCC\n+\nIIIIIIII\n@r4\nGGGGGGGG\n+\nIIIIIIII\n",
+        )
+        .unwrap();
+        fs::write(&barcodes, ">s1\nACGT\n>s2\nTTGCA\n").unwrap();
+        let opts = DemuxOptions {
+            location: BarcodeLocation::Inline,
+            max_mismatches: 1,
+            keep_barcode: false,
+        };
+        let report = demux(&reads, &barcodes, &out_dir, opts).unwrap();
+        assert_eq!(
+            report,
+            "sample\tbarcode\treads\tfraction\n\
+             s1\tACGT\t2\t0.5000\n\
+             s2\tTTGCA\t1\t0.2500\n\
+             unassigned\tn/a\t1\t0.2500\n\
+             total\tn/a\t4\t1.0000"
+        );
+        let s1 = fs::read_to_string(out_dir.join("s1.fastq")).unwrap();
+        assert_eq!(s1, "@r1\nGGGG\n+\nIIII\n@r3\nCCCC\n+\nIIII\n");
+        let unassigned = fs::read_to_string(out_dir.join("unassigned.fastq")).unwrap();
+        assert_eq!(unassigned, "@r4\nGGGGGGGG\n+\nIIIIIIII\n");
+
+        // Nothing is written if the reads can't be opened.
+        let missing_reads = dir.join("missing.fastq");
+        let missing_out_dir = dir.join("missing");
+        let opts = DemuxOptions {
+            location: BarcodeLocation::Inline,
+            max_mismatches: 1,
+            keep_barcode: false,
+        };
+        assert!(demux(&missing_reads, &barcodes, &missing_out_dir, opts).is_err());
+        assert!(!missing_out_dir.exists());
+
+        // Sample names that would escape the output directory are rejected.
+        fs::write(&barcodes, ">../escaped\nACGT\n").unwrap();
+        let opts = DemuxOptions {
+            location: BarcodeLocation::Inline,
+            max_mismatches: 1,
+            keep_barcode: false,
+        };
+        assert!(demux(&reads, &barcodes, &out_dir, opts).is_err());
+        assert!(!dir.join("escaped.fastq").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keep_barcode_needs_inline_barcodes() {
+        let opts = DemuxOptions {
+            location: BarcodeLocation::Index,
+            max_mismatches: 1,
+            keep_barcode: true,
+        };
+        let missing = Path::new("missing");
+        let err = demux(missing, missing, missing, opts).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--keep-barcode only applies to inline barcodes"
+        );
+    }
+
+    #[test]
+    fn test_read_index() {
+        let record = fastq::Record::with_attrs("r1", Some("1:N:0:ACGTACGT+TTGA"), b"ACGT", b"IIII");
+        assert_eq!(read_index(&record).unwrap(), b"ACGTACGT+TTGA");
+        let record = fastq::Record::with_attrs("r1", None, b"ACGT", b"IIII");
+        assert!(read_index(&record).is_err());
+    }
+}
@@ -0,0 +1,489 @@
+use crate::{circular, iupac};
+use anyhow::{bail, Result};
+use bio::alphabets::dna::revcomp;
+
+/// A restriction enzyme. Cut positions are given as the number of bases 5' of the cut, counted
+/// from the start of the recognition site on the top strand (`cut`) and the bottom strand
+/// (`comp_cut`, also in top-strand coordinates). For example, EcoRI (G^AATTC) cuts at 1 and 5,
+/// leaving a four base 5' overhang. Type IIS enzymes cut outside of their recognition site, so
+/// their cut positions can exceed the length of the site.
+pub struct Enzyme {
+    pub name: &'static str,
+    pub site: &'static str,
+    pub cut: usize,
+    pub comp_cut: usize,
+}
+
+impl Enzyme {
+    const fn new(name: &'static str, site: &'static str, cut: usize, comp_cut: usize) -> Enzyme {
+        Enzyme {
+            name,
+            site,
+            cut,
+            comp_cut,
+        }
+    }
+
+    fn is_palindromic(&self) -> bool {
+        revcomp(self.site.as_bytes()) == self.site.as_bytes()
+    }
+
+    /// The recognition site in the notation used by REBASE: a caret marks the top-strand cut
+    /// for enzymes that cut within their site, and enzymes that cut outside of it have the
+    /// distance to each cut in parentheses.
+    pub fn display_site(&self) -> String {
+        let len = self.site.len();
+        if self.cut <= len && self.comp_cut <= len {
+            format!("{}^{}", &self.site[..self.cut], &self.site[self.cut..])
+        } else {
+            let len = len as isize;
+            let cut = self.cut as isize - len;
+            let comp_cut = self.comp_cut as isize - len;
+            format!("{}({}/{})", self.site, cut, comp_cut)
+        }
+    }
+}
+
+/// Commercially available enzymes from REBASE that cut on one side of their recognition site.
+/// Methylation isn't taken into account, though some of these enzymes are sensitive to it: ClaI,
+/// XbaI, BspHI and NruI are blocked by overlapping dam methylation, and Acc65I, ApaI, BsaI, MscI,
+/// SfiI and StuI are impaired or blocked by overlapping dcm methylation.
+pub const ENZYMES: &[Enzyme] = &[
+    Enzyme::new("AatII", "GACGTC", 5, 1),
+    Enzyme::new("Acc65I", "GGTACC", 1, 5),
+    Enzyme::new("AccI", "GTMKAC", 2, 4),
+    Enzyme::new("AfeI", "AGCGCT", 3, 3),
+    Enzyme::new("AflII", "CTTAAG", 1, 5),
+    Enzyme::new("AgeI", "ACCGGT", 1, 5),
+    Enzyme::new("AleI", "CACNNNNGTG", 5, 5),
+    Enzyme::new("AluI", "AGCT", 2, 2),
+    Enzyme::new("ApaI", "GGGCCC", 5, 1),
+    Enzyme::new("ApaLI", "GTGCAC", 1, 5),
+    Enzyme::new("AscI", "GGCGCGCC", 2, 6),
+    Enzyme::new("AseI", "ATTAAT", 2, 4),
+    Enzyme::new("AvaI", "CYCGRG", 1, 5),
+    Enzyme::new("AvrII", "CCTAGG", 1, 5),
+    Enzyme::new("BamHI", "GGATCC", 1, 5),
+    Enzyme::new("BbsI", "GAAGAC", 8, 12),
+    Enzyme::new("BglI", "GCCNNNNNGGC", 7, 4),
+    Enzyme::new("BglII", "AGATCT", 1, 5),
+    Enzyme::new("BmtI", "GCTAGC", 5, 1),
+    Enzyme::new("BsaAI", "YACGTR", 3, 3),
+    Enzyme::new("BsaI", "GGTCTC", 7, 11),
+    Enzyme::new("BsiWI", "CGTACG", 1, 5),
+    Enzyme::new("BsmBI", "CGTCTC", 7, 11),
+    Enzyme::new("BsmI", "GAATGC", 7, 5),
+    Enzyme::new("BspEI", "TCCGGA", 1, 5),
+    Enzyme::new("BspHI", "TCATGA", 1, 5),
+    Enzyme::new("BsrGI", "TGTACA", 1, 5),
+    Enzyme::new("BstBI", "TTCGAA", 2, 4),
+    Enzyme::new("BstEII", "GGTNACC", 1, 6),
+    Enzyme::new("BtgZI", "GCGATG", 16, 20),
+    Enzyme::new("ClaI", "ATCGAT", 2, 4),
+    Enzyme::new("DraI", "TTTAAA", 3, 3),
+    Enzyme::new("EagI", "CGGCCG", 1, 5),
+    Enzyme::new("EarI", "CTCTTC", 7, 10),
+    Enzyme::new("EcoNI", "CCTNNNNNAGG", 5, 6),
+    Enzyme::new("EcoRI", "GAATTC", 1, 5),
+    Enzyme::new("EcoRV", "GATATC", 3, 3),
+    Enzyme::new("FseI", "GGCCGGCC", 6, 2),
+    Enzyme::new("HaeIII", "GGCC", 2, 2),
+    Enzyme::new("HincII", "GTYRAC", 3, 3),
+    Enzyme::new("HindIII", "AAGCTT", 1, 5),
+    Enzyme::new("HinfI", "GANTC", 1, 4),
+    Enzyme::new("HpaI", "GTTAAC", 3, 3),
+    Enzyme::new("KasI", "GGCGCC", 1, 5),
+    Enzyme::new("KpnI", "GGTACC", 5, 1),
+    Enzyme::new("MfeI", "CAATTG", 1, 5),
+    Enzyme::new("MluI", "ACGCGT", 1, 5),
+    Enzyme::new("MlyI", "GAGTC", 10, 10),
+    Enzyme::new("MscI", "TGGCCA", 3, 3),
+    Enzyme::new("MseI", "TTAA", 1, 3),
+    Enzyme::new("MspI", "CCGG", 1, 3),
+    Enzyme::new("NaeI", "GCCGGC", 3, 3),
+    Enzyme::new("NarI", "GGCGCC", 2, 4),
+    Enzyme::new("NcoI", "CCATGG", 1, 5),
+    Enzyme::new("NdeI", "CATATG", 2, 4),
+    Enzyme::new("NheI", "GCTAGC", 1, 5),
+    Enzyme::new("NlaIII", "CATG", 4, 0),
+    Enzyme::new("NotI", "GCGGCCGC", 2, 6),
+    Enzyme::new("NruI", "TCGCGA", 3, 3),
+    Enzyme::new("NsiI", "ATGCAT", 5, 1),
+    Enzyme::new("PacI", "TTAATTAA", 5, 3),
+    Enzyme::new("PaqCI", "CACCTGC", 11, 15),
+    Enzyme::new("PciI", "ACATGT", 1, 5),
+    Enzyme::new("PmeI", "GTTTAAAC", 4, 4),
+    Enzyme::new("PmlI", "CACGTG", 3, 3),
+    Enzyme::new("PsiI", "TTATAA", 3, 3),
+    Enzyme::new("PstI", "CTGCAG", 5, 1),
+    Enzyme::new("PvuI", "CGATCG", 4, 2),
+    Enzyme::new("PvuII", "CAGCTG", 3, 3),
+    Enzyme::new("SacI", "GAGCTC", 5, 1),
+    Enzyme::new("SacII", "CCGCGG", 4, 2),
+    Enzyme::new("SalI", "GTCGAC", 1, 5),
+    Enzyme::new("SapI", "GCTCTTC", 8, 11),
+    Enzyme::new("Sau3AI", "GATC", 0, 4),
+    Enzyme::new("SbfI", "CCTGCAGG", 6, 2),
+    Enzyme::new("ScaI", "AGTACT", 3, 3),
+    Enzyme::new("SfiI", "GGCCNNNNNGGCC", 8, 5),
+    Enzyme::new("SmaI", "CCCGGG", 3, 3),
+    Enzyme::new("SnaBI", "TACGTA", 3, 3),
+    Enzyme::new("SpeI", "ACTAGT", 1, 5),
+    Enzyme::new("SphI", "GCATGC", 5, 1),
+    Enzyme::new("SspI", "AATATT", 3, 3),
+    Enzyme::new("StuI", "AGGCCT", 3, 3),
+    Enzyme::new("SwaI", "ATTTAAAT", 4, 4),
+    Enzyme::new("XbaI", "TCTAGA", 1, 5),
+    Enzyme::new("XhoI", "CTCGAG", 1, 5),
+    Enzyme::new("XmaI", "CCCGGG", 1, 5),
+    Enzyme::new("XmnI", "GAANNNNTTC", 5, 5),
+    Enzyme::new("ZraI", "GACGTC", 3, 3),
+];
+
+pub fn find_enzyme(name: &str) -> Result<&'static Enzyme> {
+    match ENZYMES.iter().find(|e| e.name.eq_ignore_ascii_case(name)) {
+        Some(enzyme) => Ok(enzyme),
+        None => bail!("Unknown enzyme: '{name}' (use --list-enzymes to see all available enzymes)"),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl Strand {
+    pub fn symbol(&self) -> char {
+        match self {
+            Strand::Forward => '+',
+            Strand::Reverse => '-',
+        }
+    }
+}
+
+pub struct CutSite {
+    pub enzyme: &'static Enzyme,
+    /// Start of the recognition site on the top strand.
+    pub position: usize,
+    pub strand: Strand,
+    /// Cut positions on the top and bottom strands. For circular sequences these may fall
+    /// outside of the sequence when the site is near the origin, and must be wrapped.
+    pub top_cut: isize,
+    pub bottom_cut: isize,
+}
+
+impl CutSite {
+    fn overhang(&self, seq: &[u8]) -> String {
+        let n = seq.len() as isize;
+        let (start, end) = if self.top_cut <= self.bottom_cut {
+            (self.top_cut, self.bottom_cut)
+        } else {
+            (self.bottom_cut, self.top_cut)
+        };
+        let bases: String = (start..end)
+            .map(|i| seq[i.rem_euclid(n) as usize] as char)
+            .collect();
+        match self.top_cut.cmp(&self.bottom_cut) {
+            std::cmp::Ordering::Equal => "blunt".to_string(),
+            std::cmp::Ordering::Less => format!("5' {bases}"),
+            std::cmp::Ordering::Greater => format!("3' {bases}"),
+        }
+    }
+}
+
+/// Finds every occurrence of an enzyme's recognition site on either strand, as the start of the
+/// site on the top strand. For circular sequences, sites may span the origin.
+pub fn find_recognition_sites(enzyme: &Enzyme, seq: &[u8], circular: bool) -> Vec<(usize, Strand)> {
+    let n = seq.len();
+    let site_len = enzyme.site.len();
+    if n == 0 || site_len > n {
+        return vec![];
+    }
+    let forward = enzyme.site.as_bytes().to_vec();
+    let mut patterns = vec![(Strand::Forward, forward)];
+    if !enzyme.is_palindromic() {
+        patterns.push((Strand::Reverse, revcomp(enzyme.site.as_bytes())));
+    }
+    let scanned = if circular {
+        circular::extend_across_origin(seq, site_len - 1)
+    } else {
+        seq.to_vec()
+    };
+
+    let mut sites = vec![];
+    for (position, window) in scanned.windows(site_len).take(n).enumerate() {
+        for (strand, pattern) in &patterns {
+            if iupac::matches_at(pattern, window) {
+                sites.push((position, *strand));
+            }
+        }
+    }
+    sites
+}
+
+/// Finds every site on either strand where the enzyme will cut. For linear sequences, sites
+/// whose cuts would fall off either end of the sequence are ignored.
+pub fn find_sites(enzyme: &'static Enzyme, seq: &[u8], circular: bool) -> Vec<CutSite> {
+    let n = seq.len();
+    let site_len = enzyme.site.len();
+    let mut sites = vec![];
+    for (position, strand) in find_recognition_sites(enzyme, seq, circular) {
+        let start = position as isize;
+        let (top_cut, bottom_cut) = match strand {
+            Strand::Forward => (
+                start + enzyme.cut as isize,
+                start + enzyme.comp_cut as isize,
+            ),
+            Strand::Reverse => (
+                start + (site_len as isize - enzyme.comp_cut as isize),
+                start + (site_len as isize - enzyme.cut as isize),
+            ),
+        };
+        let in_bounds = |cut: isize| cut > 0 && cut < n as isize;
+        if !(circular || in_bounds(top_cut) && in_bounds(bottom_cut)) {
+            continue;
+        }
+        sites.push(CutSite {
+            enzyme,
+            position,
+            strand,
+            top_cut,
+            bottom_cut,
+        });
+    }
+    sites
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Fragment {
+    pub start: usize,
+    pub end: usize,
+    pub length: usize,
+}
+
+/// Splits a sequence at the given top-strand cut positions. Fragments of circular sequences
+/// that span the origin have an end that is less than their start. A circular sequence with no
+/// cuts is returned as a single fragment.
+pub fn fragments(cuts: &[usize], seq_len: usize, circular: bool) -> Vec<Fragment> {
+    let mut cuts = cuts.to_vec();
+    cuts.sort_unstable();
+    cuts.dedup();
+    if circular {
+        if cuts.is_empty() {
+            return vec![Fragment {
+                start: 0,
+                end: seq_len,
+                length: seq_len,
+            }];
+        }
+        let mut fragments: Vec<Fragment> = cuts
+            .windows(2)
+            .map(|w| Fragment {
+                start: w[0],
+                end: w[1],
+                length: w[1] - w[0],
+            })
+            .collect();
+        let last = cuts[cuts.len() - 1];
+        let first = cuts[0];
+        fragments.push(Fragment {
+            start: last,
+            end: first,
+            length: seq_len - last + first,
+        });
+        fragments
+    } else {
+        let mut boundaries = vec![0];
+        boundaries.extend(cuts);
+        boundaries.push(seq_len);
+        boundaries
+            .windows(2)
+            .map(|w| Fragment {
+                start: w[0],
+                end: w[1],
+                length: w[1] - w[0],
+            })
+            .collect()
+    }
+}
+
+pub fn list_enzymes() -> String {
+    ENZYMES
+        .iter()
+        .map(|e| format!("{}\t{}", e.name, e.display_site()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn digest(
+    seqs: Vec<String>,
+    enzyme_names: Vec<String>,
+    circular: bool,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    let seq = seqs.join("").replace(" ", "").replace("-", "");
+    iupac::confirm_valid_iupac(&seq)?;
+    let seq = seq.to_ascii_uppercase().replace("U", "T").into_bytes();
+    let n = seq.len();
+
+    // Without any enzymes we just summarize which enzymes cut and how often.
+    if enzyme_names.is_empty() {
+        let mut output = vec!["enzyme\tsite\tcuts".to_string()];
+        for enzyme in ENZYMES {
+            let count = find_sites(enzyme, &seq, circular).len();
+            if count > 0 {
+                output.push(format!(
+                    "{}\t{}\t{}",
+                    enzyme.name,
+                    enzyme.display_site(),
+                    count
+                ));
+            }
+        }
+        return Ok(output.join("\n"));
+    }
+
+    let enzymes = enzyme_names
+        .iter()
+        .map(|name| find_enzyme(name))
+        .collect::<Result<Vec<_>>>()?;
+    let sites: Vec<CutSite> = enzymes
+        .into_iter()
+        .flat_map(|enzyme| find_sites(enzyme, &seq, circular))
+        .collect();
+
+    let offset = if use_0_based_coords { 0 } else { 1 };
+    let mut output = vec!["enzyme\tsite\tstrand\tstart\tend\tcut\toverhang".to_string()];
+    for site in &sites {
+        let end = (site.position + site.enzyme.site.len() - 1) % n + 1;
+        output.push(format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            site.enzyme.name,
+            site.enzyme.display_site(),
+            site.strand.symbol(),
+            site.position + offset,
+            end,
+            site.top_cut.rem_euclid(n as isize) as usize + offset,
+            site.overhang(&seq)
+        ));
+    }
+
+    let cuts: Vec<usize> = sites
+        .iter()
+        .map(|site| site.top_cut.rem_euclid(n as isize) as usize)
+        .collect();
+    output.push("".to_string());
+    output.push("fragment\tstart\tend\tlength".to_string());
+    for (i, fragment) in fragments(&cuts, n, circular).iter().enumerate() {
+        let end = if fragment.end == 0 { n } else { fragment.end };
+        output.push(format!(
+            "{}\t{}\t{}\t{}",
+            i + 1,
+            fragment.start + offset,
+            end,
+            fragment.length
+        ));
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_sites_palindrome_counted_once() {
+        let enzyme = find_enzyme("EcoRI").unwrap();
+        let sites = find_sites(enzyme, b"AAGAATTCAA", false);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].position, 2);
+        assert_eq!(sites[0].top_cut, 3);
+        assert_eq!(sites[0].overhang(b"AAGAATTCAA"), "5' AATT");
+    }
+
+    #[test]
+    fn test_find_sites_reverse_strand() {
+        // GAGACC is the reverse complement of the BsaI site, so it cuts upstream of the site.
+        let enzyme = find_enzyme("bsai").unwrap();
+        let seq = b"AAAAAAAAAAAAGAGACCAA";
+        let sites = find_sites(enzyme, seq, false);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].strand, Strand::Reverse);
+        assert_eq!(sites[0].top_cut, 7);
+        assert_eq!(sites[0].bottom_cut, 11);
+        assert_eq!(sites[0].overhang(seq), "5' AAAA");
+    }
+
+    #[test]
+    fn test_find_sites_ambiguous_recognition_sequence() {
+        let enzyme = find_enzyme("HinfI").unwrap();
+        let sites = find_sites(enzyme, b"TTGACTCTTGAGTCTT", false);
+        assert_eq!(sites.len(), 2);
+    }
+
+    #[test]
+    fn test_find_sites_across_origin() {
+        let enzyme = find_enzyme("EcoRI").unwrap();
+        let seq = b"TTCAAAAAAAAGAA";
+        assert!(find_sites(enzyme, seq, false).is_empty());
+        let sites = find_sites(enzyme, seq, true);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].position, 11);
+        assert_eq!(sites[0].overhang(seq), "5' AATT");
+    }
+
+    #[test]
+    fn test_fragments_linear() {
+        let actual: Vec<usize> = fragments(&[30, 10], 100, false)
+            .iter()
+            .map(|f| f.length)
+            .collect();
+        assert_eq!(actual, vec![10, 20, 70]);
+    }
+
+    #[test]
+    fn test_fragments_circular() {
+        let actual = fragments(&[30, 10], 100, true);
+        assert_eq!(actual.len(), 2);
+        assert_eq!(
+            actual[1],
+            Fragment {
+                start: 30,
+                end: 10,
+                length: 80
+            }
+        );
+    }
+
+    #[test]
+    fn test_digest() {
+        let actual = digest(
+            vec!["AAGAATTCAAAAGGATCCAA".to_string()],
+            vec!["EcoRI".to_string(), "BamHI".to_string()],
+            false,
+            false,
+        )
+        .unwrap();
+        let expected = "enzyme\tsite\tstrand\tstart\tend\tcut\toverhang\n\
+                        EcoRI\tG^AATTC\t+\t3\t8\t4\t5' AATT\n\
+                        BamHI\tG^GATCC\t+\t13\t18\t14\t5' GATC\n\
+                        \n\
+                        fragment\tstart\tend\tlength\n\
+                        1\t1\t3\t3\n\
+                        2\t4\t13\t10\n\
+                        3\t14\t20\t7";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_digest_unknown_enzyme() {
+        assert!(digest(
+            vec!["GAATTC".to_string()],
+            vec!["NotAnEnzyme".to_string()],
+            false,
+            false
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::fasta;
+use crate::iupac;
+use anyhow::{bail, Result};
+use bio::alignment::distance::{hamming, levenshtein};
+use bio::alphabets::dna::revcomp;
+use clap::ValueEnum;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Metric {
+    /// Number of positions that differ, for sequences of the same length
+    Hamming,
+    /// Number of substitutions, insertions and deletions
+    Levenshtein,
+}
+
+pub struct DistanceOptions {
+    pub ignore_case: bool,
+    /// Compare each sequence with the other and with its reverse complement, and take the
+    /// smaller distance.
+    pub ignore_strand: bool,
+}
+
+/// Distance between two sequences, or an error if Hamming distance is asked for between
+/// sequences of different lengths.
+pub fn distance(metric: Metric, a: &[u8], b: &[u8], opts: &DistanceOptions) -> Result<usize> {
+    let (a, b) = if opts.ignore_case {
+        (a.to_ascii_uppercase(), b.to_ascii_uppercase())
+    } else {
+        (a.to_vec(), b.to_vec())
+    };
+    let measure = |b: &[u8]| -> Result<usize> {
+        match metric {
+            Metric::Hamming => {
+                if a.len() != b.len() {
+                    bail!(
+                        "Hamming distance needs sequences of the same length (got {} and {})",
+                        a.len(),
+                        b.len()
+                    );
+                }
+                Ok(hamming(&a, b) as usize)
+            }
+            Metric::Levenshtein => Ok(levenshtein(&a, b) as usize),
+        }
+    };
+    let forward = measure(&b)?;
+    if opts.ignore_strand {
+        Ok(forward.min(measure(&revcomp(&b))?))
+    } else {
+        Ok(forward)
+    }
+}
+
+/// Distances between every pair of sequences, as a symmetric matrix.
+pub fn distance_matrix(
+    metric: Metric,
+    seqs: &[&[u8]],
+    opts: &DistanceOptions,
+) -> Result<Vec<Vec<usize>>> {
+    let mut matrix = vec![vec![0; seqs.len()]; seqs.len()];
+    for i in 0..seqs.len() {
+        for j in i + 1..seqs.len() {
+            let d = distance(metric, seqs[i], seqs[j], opts)?;
+            matrix[i][j] = d;
+            matrix[j][i] = d;
+        }
+    }
+    Ok(matrix)
+}
+
+/// Reports the distance between two sequences, or a matrix of distances between more. Sequences
+/// given on the command line are labelled by themselves, and FASTA records by their IDs.
+pub fn distances(
+    metric: Metric,
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    opts: DistanceOptions,
+) -> Result<String> {
+    let records: Vec<(String, String)> = match fasta {
+        Some(path) => fasta::read_records(path)?
+            .into_iter()
+            .map(|record| (record.id, record.seq))
+            .collect(),
+        None => seqs
+            .into_iter()
+            .map(|seq| {
+                let seq = seq.replace("-", "");
+                (seq.clone(), seq)
+            })
+            .collect(),
+    };
+    if records.len() < 2 {
+        bail!("At least two sequences are needed");
+    }
+    if opts.ignore_strand {
+        for (_, seq) in &records {
+            iupac::confirm_valid_iupac(seq)?;
+        }
+    }
+    let seqs: Vec<&[u8]> = records.iter().map(|(_, seq)| seq.as_bytes()).collect();
+    let matrix = distance_matrix(metric, &seqs, &opts)?;
+    if records.len() == 2 {
+        return Ok(matrix[0][1].to_string());
+    }
+    let labels: Vec<&str> = records.iter().map(|(label, _)| label.as_str()).collect();
+    let mut output = vec![format!("\t{}", labels.join("\t"))];
+    for (label, row) in labels.iter().zip(&matrix) {
+        let row: Vec<String> = row.iter().map(|d| d.to_string()).collect();
+        output.push(format!("{}\t{}", label, row.join("\t")));
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hamming() {
+        let mut opts = DistanceOptions {
+            ignore_case: false,
+            ignore_strand: false,
+        };
+        assert_eq!(
+            distance(Metric::Hamming, b"ACGT", b"ACCT", &opts).unwrap(),
+            1
+        );
+        assert_eq!(
+            distance(Metric::Hamming, b"ACGT", b"acgt", &opts).unwrap(),
+            4
+        );
+        assert!(distance(Metric::Hamming, b"ACGT", b"ACG", &opts).is_err());
+        opts.ignore_case = true;
+        assert_eq!(
+            distance(Metric::Hamming, b"ACGT", b"acgt", &opts).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        let opts = DistanceOptions {
+            ignore_case: false,
+            ignore_strand: false,
+        };
+        assert_eq!(
+            distance(Metric::Levenshtein, b"ACGTACGT", b"ACGACGT", &opts).unwrap(),
+            1
+        );
+        assert_eq!(
+            distance(Metric::Levenshtein, b"", b"ACG", &opts).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_ignore_strand() {
+        // AACCG is the reverse complement of CGGTT.
+        let mut opts = DistanceOptions {
+            ignore_case: false,
+            ignore_strand: false,
+        };
+        assert_eq!(
+            distance(Metric::Hamming, b"AACCG", b"CGGTT", &opts).unwrap(),
+            5
+        );
+        opts.ignore_strand = true;
+        assert_eq!(
+            distance(Metric::Hamming, b"AACCG", b"CGGTT", &opts).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_distances_output() {
+        let seqs = vec!["ACGT".to_string(), "ACCT".to_string()];
+        let opts = DistanceOptions {
+            ignore_case: false,
+            ignore_strand: false,
+        };
+        assert_eq!(distances(Metric::Hamming, seqs, None, opts).unwrap(), "1");
+        let seqs = vec!["ACGT".to_string(), "ACCT".to_string(), "TCCA".to_string()];
+        let opts = DistanceOptions {
+            ignore_case: false,
+            ignore_strand: false,
+        };
+        assert_eq!(
+            distances(Metric::Hamming, seqs, None, opts).unwrap(),
+            "\tACGT\tACCT\tTCCA\nACGT\t0\t1\t3\nACCT\t1\t0\t2\nTCCA\t3\t2\t0"
+        );
+        let seqs = vec!["ACGT".to_string()];
+        let opts = DistanceOptions {
+            ignore_case: false,
+            ignore_strand: false,
+        };
+        assert!(distances(Metric::Hamming, seqs, None, opts).is_err());
+    }
+}
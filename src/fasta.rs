@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use bio::io::fasta;
+use std::path::Path;
+
+pub struct Record {
+    pub id: String,
+    pub seq: String,
+}
+
+pub fn read_records(path: &Path) -> Result<Vec<Record>> {
+    let reader = fasta::Reader::from_file(path)
+        .with_context(|| format!("Failed to open FASTA file {}", path.display()))?;
+    reader
+        .records()
+        .map(|record| {
+            let record = record.context("Failed to parse FASTA record")?;
+            let seq = String::from_utf8(record.seq().to_vec())
+                .with_context(|| format!("Invalid sequence in record {}", record.id()))?;
+            Ok(Record {
+                id: record.id().to_string(),
+                seq,
+            })
+        })
+        .collect()
+}
+
+/// Sequences to operate on: either every record in a FASTA file, or a single sequence given on
+/// the command line, with spaces and dashes removed. Command-line sequences have no ID.
+pub fn records_or_sequence(seqs: Vec<String>, fasta: Option<&Path>) -> Result<Vec<Record>> {
+    match fasta {
+        Some(path) => read_records(path),
+        None => Ok(vec![Record {
+            id: "".to_string(),
+            seq: seqs.join("").replace(" ", "").replace("-", ""),
+        }]),
+    }
+}
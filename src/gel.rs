@@ -0,0 +1,281 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Ladder {
+    #[value(name = "1kb")]
+    OneKb,
+    #[value(name = "100bp")]
+    HundredBp,
+    None,
+}
+
+impl Ladder {
+    fn sizes(&self) -> &'static [usize] {
+        match self {
+            Ladder::OneKb => &[10000, 8000, 6000, 5000, 4000, 3000, 2000, 1500, 1000, 500],
+            Ladder::HundredBp => &[
+                1517, 1200, 1000, 900, 800, 700, 600, 500, 400, 300, 200, 100,
+            ],
+            Ladder::None => &[],
+        }
+    }
+}
+
+/// Approximate smallest and largest linear fragments (in bp) that are resolved by an agarose gel
+/// of a given percentage. Fragments outside of this range pile up at the bottom or top of the gel.
+const RESOLUTION: &[(f64, f64, f64)] = &[
+    (0.5, 1000.0, 30000.0),
+    (0.7, 800.0, 12000.0),
+    (1.0, 500.0, 10000.0),
+    (1.2, 400.0, 7000.0),
+    (1.5, 200.0, 3000.0),
+    (2.0, 50.0, 2000.0),
+    (3.0, 10.0, 1000.0),
+];
+
+fn resolution_range(percent: f64) -> Result<(f64, f64)> {
+    let (lowest, _, _) = RESOLUTION[0];
+    let (highest, _, _) = RESOLUTION[RESOLUTION.len() - 1];
+    if !(lowest..=highest).contains(&percent) {
+        bail!("Gel percentage must be between {lowest} and {highest}");
+    }
+    for pair in RESOLUTION.windows(2) {
+        let (p0, min0, max0) = pair[0];
+        let (p1, min1, max1) = pair[1];
+        if percent <= p1 {
+            // Interpolate in log space, since migration is roughly linear in log(size).
+            let t = (percent - p0) / (p1 - p0);
+            let min = (min0.ln() + t * (min1.ln() - min0.ln())).exp();
+            let max = (max0.ln() + t * (max1.ln() - max0.ln())).exp();
+            return Ok((min, max));
+        }
+    }
+    unreachable!()
+}
+
+/// Relative distance a fragment travels from the well, from 0.0 (the well) to 1.0 (the bottom
+/// of the gel).
+fn migration(size: usize, percent: f64) -> Result<f64> {
+    let (min, max) = resolution_range(percent)?;
+    let distance = (max.log10() - (size as f64).log10()) / (max.log10() - min.log10());
+    Ok(distance.clamp(0.0, 1.0))
+}
+
+pub struct Lane {
+    pub label: String,
+    pub sizes: Vec<usize>,
+}
+
+/// Parses lanes given as comma-separated fragment sizes, e.g. "3000,1500,500".
+pub fn parse_lanes(lanes: Vec<String>) -> Result<Vec<Lane>> {
+    lanes
+        .iter()
+        .enumerate()
+        .map(|(i, lane)| {
+            let sizes = lane
+                .split(',')
+                .filter(|size| !size.trim().is_empty())
+                .map(|size| {
+                    size.trim()
+                        .parse::<usize>()
+                        .with_context(|| format!("Invalid fragment size: '{size}'"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if sizes.contains(&0) {
+                bail!("Fragment sizes must be greater than zero");
+            }
+            Ok(Lane {
+                label: (i + 1).to_string(),
+                sizes,
+            })
+        })
+        .collect()
+}
+
+/// Labels and fragment sizes of every lane, with the ladder (if any) in the first lane.
+fn with_ladder(lanes: &[Lane], ladder: Ladder) -> Vec<(&str, &[usize])> {
+    let mut all_lanes: Vec<(&str, &[usize])> = vec![];
+    if !ladder.sizes().is_empty() {
+        all_lanes.push(("L", ladder.sizes()));
+    }
+    for lane in lanes {
+        all_lanes.push((&lane.label, &lane.sizes));
+    }
+    all_lanes
+}
+
+const ASCII_HEIGHT: usize = 30;
+const ASCII_LANE_WIDTH: usize = 5;
+
+pub fn render_ascii(lanes: &[Lane], ladder: Ladder, percent: f64) -> Result<String> {
+    let ladder_sizes = ladder.sizes();
+    let all_lanes = with_ladder(lanes, ladder);
+
+    // Each cell holds the number of fragments that migrated to that row.
+    let mut rows = vec![vec![0usize; all_lanes.len()]; ASCII_HEIGHT];
+    for (column, (_, sizes)) in all_lanes.iter().enumerate() {
+        for size in sizes.iter() {
+            let row = (migration(*size, percent)? * (ASCII_HEIGHT - 1) as f64).round() as usize;
+            rows[row][column] += 1;
+        }
+    }
+
+    // Ladder sizes are printed to the left of the gel.
+    let mut row_labels = vec!["".to_string(); ASCII_HEIGHT];
+    for size in ladder_sizes {
+        let row = (migration(*size, percent)? * (ASCII_HEIGHT - 1) as f64).round() as usize;
+        if row_labels[row].is_empty() {
+            row_labels[row] = size.to_string();
+        }
+    }
+    let label_width = row_labels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+    let mut output = vec![];
+    let header: Vec<String> = all_lanes
+        .iter()
+        .map(|(label, _)| format!("{:^width$}", label, width = ASCII_LANE_WIDTH))
+        .collect();
+    output.push(
+        format!("{:>label_width$} {}", "", header.join(" "))
+            .trim_end()
+            .to_string(),
+    );
+    let wells: Vec<String> = all_lanes
+        .iter()
+        .map(|_| format!("[{}]", " ".repeat(ASCII_LANE_WIDTH - 2)))
+        .collect();
+    output.push(format!("{:>label_width$} {}", "", wells.join(" ")));
+    for (row, counts) in rows.iter().enumerate() {
+        let cells: Vec<String> = counts
+            .iter()
+            .map(|count| match count {
+                0 => " ".repeat(ASCII_LANE_WIDTH),
+                1 => "=".repeat(ASCII_LANE_WIDTH),
+                _ => "#".repeat(ASCII_LANE_WIDTH),
+            })
+            .collect();
+        let line = format!("{:>label_width$} {}", row_labels[row], cells.join(" "));
+        output.push(line.trim_end().to_string());
+    }
+    Ok(output.join("\n"))
+}
+
+const SVG_LANE_WIDTH: usize = 40;
+const SVG_LANE_GAP: usize = 16;
+const SVG_LABEL_WIDTH: usize = 50;
+const SVG_TOP: usize = 40;
+const SVG_GEL_HEIGHT: usize = 400;
+const SVG_BAND_HEIGHT: usize = 4;
+
+pub fn render_svg(lanes: &[Lane], ladder: Ladder, percent: f64) -> Result<String> {
+    let ladder_sizes = ladder.sizes();
+    let all_lanes = with_ladder(lanes, ladder);
+    let width = SVG_LABEL_WIDTH + all_lanes.len() * (SVG_LANE_WIDTH + SVG_LANE_GAP) + SVG_LANE_GAP;
+    let height = SVG_TOP + SVG_GEL_HEIGHT + 20;
+
+    // Band brightness is proportional to mass, which for equimolar fragments scales with size.
+    let max_size = all_lanes
+        .iter()
+        .flat_map(|(_, sizes)| sizes.iter())
+        .max()
+        .copied()
+        .unwrap_or(1) as f64;
+
+    let mut output = vec![
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        ),
+        format!(r##"<rect x="0" y="0" width="{width}" height="{height}" fill="#1a1a2e"/>"##),
+        format!(
+            r##"<text x="{}" y="14" font-family="monospace" font-size="12" fill="#cccccc" text-anchor="middle">{percent}% agarose</text>"##,
+            width / 2
+        ),
+    ];
+    for (i, (label, sizes)) in all_lanes.iter().enumerate() {
+        let x = SVG_LABEL_WIDTH + SVG_LANE_GAP + i * (SVG_LANE_WIDTH + SVG_LANE_GAP);
+        output.push(format!(
+            r##"<text x="{}" y="{}" font-family="monospace" font-size="12" fill="#cccccc" text-anchor="middle">{label}</text>"##,
+            x + SVG_LANE_WIDTH / 2,
+            SVG_TOP - 14
+        ));
+        output.push(format!(
+            r##"<rect x="{x}" y="{}" width="{SVG_LANE_WIDTH}" height="6" fill="#000000" stroke="#555555"/>"##,
+            SVG_TOP - 8
+        ));
+        for size in sizes.iter() {
+            let y = SVG_TOP + (migration(*size, percent)? * SVG_GEL_HEIGHT as f64).round() as usize;
+            let opacity = 0.35 + 0.65 * (*size as f64 / max_size);
+            output.push(format!(
+                r##"<rect x="{x}" y="{y}" width="{SVG_LANE_WIDTH}" height="{SVG_BAND_HEIGHT}" fill="#ffffff" fill-opacity="{opacity:.2}"><title>{size} bp</title></rect>"##
+            ));
+        }
+    }
+    for size in ladder_sizes {
+        let y = SVG_TOP + (migration(*size, percent)? * SVG_GEL_HEIGHT as f64).round() as usize;
+        output.push(format!(
+            r##"<text x="{}" y="{}" font-family="monospace" font-size="10" fill="#cccccc" text-anchor="end">{size}</text>"##,
+            SVG_LABEL_WIDTH + SVG_LANE_GAP / 2,
+            y + SVG_BAND_HEIGHT
+        ));
+    }
+    output.push("</svg>".to_string());
+    Ok(output.join("\n"))
+}
+
+pub fn gel(lanes: Vec<String>, ladder: Ladder, percent: f64, svg: bool) -> Result<String> {
+    let lanes = parse_lanes(lanes)?;
+    if lanes.is_empty() && ladder.sizes().is_empty() {
+        bail!("Nothing to show: give at least one lane of fragment sizes or a ladder");
+    }
+    if svg {
+        render_svg(&lanes, ladder, percent)
+    } else {
+        render_ascii(&lanes, ladder, percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_is_monotonic() {
+        let large = migration(5000, 1.0).unwrap();
+        let small = migration(1000, 1.0).unwrap();
+        assert!(large < small);
+    }
+
+    #[test]
+    fn test_migration_clamped_outside_resolution_range() {
+        assert_eq!(migration(50000, 1.0).unwrap(), 0.0);
+        assert_eq!(migration(20, 1.0).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_higher_percentage_resolves_smaller_fragments() {
+        assert_eq!(migration(100, 1.0).unwrap(), 1.0);
+        assert!(migration(100, 2.0).unwrap() < 1.0);
+    }
+
+    #[test]
+    fn test_invalid_percentage() {
+        assert!(migration(1000, 10.0).is_err());
+    }
+
+    #[test]
+    fn test_parse_lanes() {
+        let lanes = parse_lanes(vec!["3000,1500, 500".to_string(), "400".to_string()]).unwrap();
+        assert_eq!(lanes[0].sizes, vec![3000, 1500, 500]);
+        assert_eq!(lanes[1].label, "2");
+        assert!(parse_lanes(vec!["3000,1.5kb".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_render_ascii_comigrating_bands() {
+        let lanes = parse_lanes(vec!["1000,1000".to_string()]).unwrap();
+        let gel = render_ascii(&lanes, Ladder::None, 1.0).unwrap();
+        assert!(gel.contains("#####"));
+        assert!(!gel.contains("====="));
+    }
+}
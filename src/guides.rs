@@ -0,0 +1,274 @@
+use crate::circular;
+use crate::compute_gc_content;
+use crate::digest::Strand;
+use crate::fasta;
+use crate::iupac;
+use anyhow::{bail, Result};
+use bio::alphabets::dna::revcomp;
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Four or more Ts in a row terminate transcription by RNA polymerase III, so guides expressed
+/// from U6 or H1 promoters shouldn't contain them.
+const POLY_T: &[u8] = b"TTTT";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Nuclease {
+    /// SpCas9: 20 nt protospacers followed by an NGG PAM
+    Cas9,
+    /// Cas12a (Cpf1): 23 nt protospacers preceded by a TTTV PAM
+    Cas12a,
+}
+
+impl Nuclease {
+    fn default_pam(&self) -> &'static str {
+        match self {
+            Nuclease::Cas9 => "NGG",
+            Nuclease::Cas12a => "TTTV",
+        }
+    }
+
+    fn default_length(&self) -> usize {
+        match self {
+            Nuclease::Cas9 => 20,
+            Nuclease::Cas12a => 23,
+        }
+    }
+
+    /// Whether the PAM is 5' of the protospacer (on the non-target strand).
+    fn pam_is_5_prime(&self) -> bool {
+        matches!(self, Nuclease::Cas12a)
+    }
+}
+
+pub struct GuideOptions {
+    pub nuclease: Nuclease,
+    pub pam: Option<String>,
+    pub length: Option<usize>,
+    pub max_homopolymer: usize,
+    pub circular: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Guide {
+    /// Zero-based start of the protospacer on the forward strand. For circular sequences, the
+    /// protospacer may extend past the origin.
+    pub start: usize,
+    pub strand: Strand,
+    /// The protospacer and PAM, read 5' to 3' on the strand of the guide.
+    pub protospacer: String,
+    pub pam: String,
+}
+
+/// Finds every protospacer with an adjacent PAM on either strand. Protospacers containing
+/// ambiguous bases are skipped.
+pub fn find_guides(
+    seq: &[u8],
+    pam: &[u8],
+    length: usize,
+    pam_is_5_prime: bool,
+    circular: bool,
+) -> Vec<Guide> {
+    let n = seq.len();
+    let site_length = length + pam.len();
+    if site_length > n {
+        return vec![];
+    }
+    let mut guides = vec![];
+    for strand in [Strand::Forward, Strand::Reverse] {
+        let stranded = match strand {
+            Strand::Forward => seq.to_vec(),
+            Strand::Reverse => revcomp(seq),
+        };
+        let scanned = if circular {
+            circular::extend_across_origin(&stranded, site_length - 1)
+        } else {
+            stranded
+        };
+        for (i, site) in scanned.windows(site_length).take(n).enumerate() {
+            let (pam_bases, protospacer, offset) = if pam_is_5_prime {
+                (&site[..pam.len()], &site[pam.len()..], pam.len())
+            } else {
+                (&site[length..], &site[..length], 0)
+            };
+            if !iupac::matches_at(pam, pam_bases)
+                || !protospacer
+                    .iter()
+                    .all(|b| matches!(b.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T'))
+            {
+                continue;
+            }
+            let start = match strand {
+                Strand::Forward => (i + offset) % n,
+                Strand::Reverse => (2 * n - i - offset - length) % n,
+            };
+            guides.push(Guide {
+                start,
+                strand,
+                protospacer: String::from_utf8_lossy(protospacer).to_string(),
+                pam: String::from_utf8_lossy(pam_bases).to_string(),
+            });
+        }
+    }
+    guides.sort_by_key(|guide| (guide.start, guide.strand == Strand::Reverse));
+    guides
+}
+
+fn flags(protospacer: &str, max_homopolymer: usize) -> Vec<&'static str> {
+    let protospacer = protospacer.to_ascii_uppercase();
+    let bases = protospacer.as_bytes();
+    let mut flags = vec![];
+    if bases.windows(POLY_T.len()).any(|w| w == POLY_T) {
+        flags.push("poly_t");
+    }
+    if bases
+        .chunk_by(|a, b| a == b)
+        .any(|run| run.len() > max_homopolymer)
+    {
+        flags.push("homopolymer");
+    }
+    flags
+}
+
+pub fn guides(
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    opts: GuideOptions,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    let pam = opts
+        .pam
+        .unwrap_or_else(|| opts.nuclease.default_pam().to_string());
+    if pam.is_empty() {
+        bail!("PAM is empty");
+    }
+    iupac::confirm_valid_iupac(&pam)?;
+    let length = opts.length.unwrap_or(opts.nuclease.default_length());
+    if length == 0 {
+        bail!("Protospacer length must be greater than zero");
+    }
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let offset = if use_0_based_coords { 0 } else { 1 };
+    let header = "start\tend\tstrand\tprotospacer\tpam\tgc\tflags";
+    let mut output = vec![];
+    if fasta.is_some() {
+        output.push(format!("id\t{header}"));
+    } else {
+        output.push(header.to_string());
+    }
+    for record in records {
+        iupac::confirm_valid_iupac(&record.seq)?;
+        let n = record.seq.len();
+        let found = find_guides(
+            record.seq.as_bytes(),
+            pam.as_bytes(),
+            length,
+            opts.nuclease.pam_is_5_prime(),
+            opts.circular,
+        );
+        for guide in found {
+            let gc = compute_gc_content(vec![guide.protospacer.clone()])?;
+            let flags = flags(&guide.protospacer, opts.max_homopolymer);
+            let line = format!(
+                "{}\t{}\t{}\t{}\t{}\t{:.4}\t{}",
+                guide.start + offset,
+                (guide.start + length - 1) % n + 1,
+                guide.strand.symbol(),
+                guide.protospacer,
+                guide.pam,
+                gc,
+                if flags.is_empty() {
+                    "-".to_string()
+                } else {
+                    flags.join(",")
+                }
+            );
+            if record.id.is_empty() {
+                output.push(line);
+            } else {
+                output.push(format!("{}\t{}", record.id, line));
+            }
+        }
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cas9_guides_on_both_strands() {
+        // A forward NGG PAM after the first 20 bases, and a CCN (NGG on the reverse strand) at
+        // the start.
+        let seq = b"CCAACGTACGTACGTACGTACGTAGGTT";
+        let guides = find_guides(seq, b"NGG", 20, false, false);
+        assert_eq!(guides.len(), 2);
+        // The protospacer is its own reverse complement, so both guides target the same bases.
+        assert_eq!(guides[0].strand, Strand::Forward);
+        assert_eq!(guides[0].start, 3);
+        assert_eq!(guides[0].protospacer, "ACGTACGTACGTACGTACGT");
+        assert_eq!(guides[0].pam, "AGG");
+        assert_eq!(guides[1].strand, Strand::Reverse);
+        assert_eq!(guides[1].start, 3);
+        assert_eq!(guides[1].protospacer, "ACGTACGTACGTACGTACGT");
+        assert_eq!(guides[1].pam, "TGG");
+    }
+
+    #[test]
+    fn test_cas12a_pam_is_5_prime() {
+        let seq = b"TTTAACGTACGTACGTACGTACGTACG";
+        let guides = find_guides(seq, b"TTTV", 23, true, false);
+        assert_eq!(guides.len(), 1);
+        assert_eq!(guides[0].start, 4);
+        assert_eq!(guides[0].pam, "TTTA");
+        assert_eq!(guides[0].protospacer, "ACGTACGTACGTACGTACGTACG");
+    }
+
+    #[test]
+    fn test_guide_across_origin() {
+        // The PAM is at the start of the sequence, and the protospacer at the end.
+        let seq = b"AGGTTTTTTTTTTACGTACGTACGTACGTACGT";
+        assert!(find_guides(seq, b"NGG", 20, false, false)
+            .iter()
+            .all(|g| g.strand == Strand::Reverse));
+        let guides = find_guides(seq, b"NGG", 20, false, true);
+        let forward: Vec<&Guide> = guides
+            .iter()
+            .filter(|g| g.strand == Strand::Forward)
+            .collect();
+        assert_eq!(forward.len(), 1);
+        assert_eq!(forward[0].start, 13);
+        assert_eq!(forward[0].pam, "AGG");
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(flags("ACGTTTTACG", 4), vec!["poly_t"]);
+        assert_eq!(flags("ACGGGGGACG", 4), vec!["homopolymer"]);
+        assert_eq!(flags("acgttttttg", 4), vec!["poly_t", "homopolymer"]);
+        assert!(flags("ACGTACGTAC", 4).is_empty());
+    }
+
+    #[test]
+    fn test_guides_output() {
+        let opts = GuideOptions {
+            nuclease: Nuclease::Cas9,
+            pam: None,
+            length: None,
+            max_homopolymer: 4,
+            circular: false,
+        };
+        let actual = guides(
+            vec!["TTACGTACGTACGTACGTACGTAGGTT".to_string()],
+            None,
+            opts,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            actual,
+            "start\tend\tstrand\tprotospacer\tpam\tgc\tflags\n3\t22\t+\tACGTACGTACGTACGTACGT\tAGG\t0.5000\t-"
+        );
+    }
+}
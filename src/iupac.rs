@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+
+/// Returns the set of unambiguous bases represented by an IUPAC nucleotide code. U is treated
+/// as T. Returns `None` for characters that are not IUPAC nucleotide codes.
+pub fn bases(code: u8) -> Option<&'static [u8]> {
+    let bases: &'static [u8] = match code.to_ascii_uppercase() {
+        b'A' => b"A",
+        b'C' => b"C",
+        b'G' => b"G",
+        b'T' | b'U' => b"T",
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => return None,
+    };
+    Some(bases)
+}
+
+/// Whether a base in a sequence is matched by a (possibly degenerate) base in a pattern. An
+/// ambiguous base in the sequence only matches if every base it could stand for is allowed by
+/// the pattern, so an N in the sequence will only ever be matched by an N in the pattern.
+/// Comparisons are case-insensitive.
+pub fn matches(pattern: u8, base: u8) -> bool {
+    match (bases(pattern), bases(base)) {
+        (Some(allowed), Some(candidates)) => candidates.iter().all(|b| allowed.contains(b)),
+        _ => false,
+    }
+}
+
+/// Whether a pattern matches the sequence at every position, without any mismatches.
+pub fn matches_at(pattern: &[u8], seq: &[u8]) -> bool {
+    pattern.len() == seq.len() && pattern.iter().zip(seq).all(|(&p, &s)| matches(p, s))
+}
+
+pub fn confirm_valid_iupac(seq: &str) -> Result<()> {
+    for (i, c) in seq.chars().enumerate() {
+        if !c.is_ascii() || bases(c as u8).is_none() {
+            return Err(anyhow!("Invalid base: '{c}' at position {i}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_degenerate_pattern() {
+        assert!(matches(b'R', b'A'));
+        assert!(matches(b'R', b'g'));
+        assert!(!matches(b'R', b'C'));
+        assert!(matches(b'N', b'T'));
+    }
+
+    #[test]
+    fn test_ambiguous_base_in_sequence() {
+        assert!(matches(b'N', b'N'));
+        assert!(matches(b'N', b'R'));
+        assert!(!matches(b'A', b'N'));
+        assert!(!matches(b'R', b'N'));
+    }
+
+    #[test]
+    fn test_confirm_valid_iupac() {
+        assert!(confirm_valid_iupac("ACGTNRYU").is_ok());
+        assert!(confirm_valid_iupac("ACGXT").is_err());
+    }
+}
@@ -1,10 +1,33 @@
 use anyhow::{anyhow, bail, Context, Result};
 use bio::alignment::pairwise::Aligner;
 use bio::alignment::{Alignment, AlignmentOperation};
-use bio::alphabets::dna::revcomp;
-use bio::seq_analysis::gc::gc_content as rustbio_gc_content;
-use clap::{Parser, Subcommand};
+use bio::alphabets::dna::{complement, revcomp};
+use bio::alphabets::rna::{complement as rna_complement, revcomp as rna_revcomp};
+use clap::{ArgGroup, Parser, Subcommand};
 use std::cmp;
+use std::path::PathBuf;
+
+mod backtranslate;
+mod barcodes;
+mod circular;
+mod codons;
+mod complexity;
+mod composition;
+mod demux;
+mod digest;
+mod distance;
+mod fasta;
+mod gel;
+mod guides;
+mod iupac;
+mod molecular_weight;
+mod offtargets;
+mod protein;
+mod pwm;
+mod repeats;
+mod search;
+mod synthesis;
+mod trim;
 
 #[derive(Parser, Debug)]
 #[command(version, about="Simple bioinformatics tools for sequence analysis and manipulation", long_about = None)]
@@ -19,16 +42,539 @@ enum Commands {
     ReverseComplement {
         #[arg(help = "RNA/DNA sequence")]
         seqs: Vec<String>,
+        #[arg(
+            long,
+            help = "Treat the sequence as RNA. This is only needed if it contains no uracil."
+        )]
+        rna: bool,
+        #[arg(long, help = "Treat the sequence as circular (e.g. a plasmid)")]
+        circular: bool,
+        #[arg(
+            long,
+            requires = "circular",
+            help = "Rotate the circular sequence to begin at this position (one-based) before reverse complementing it"
+        )]
+        origin: Option<usize>,
+    },
+    #[command(about = "Converts a nucleic acid sequence to its complement.")]
+    Complement {
+        #[arg(help = "RNA/DNA sequence")]
+        seqs: Vec<String>,
+        #[arg(
+            long,
+            help = "Treat the sequence as RNA. This is only needed if it contains no uracil."
+        )]
+        rna: bool,
+    },
+    #[command(about = "Reverses a sequence.")]
+    Reverse {
+        #[arg(help = "DNA/RNA/protein sequence")]
+        seqs: Vec<String>,
+    },
+    #[command(about = "Transcribes DNA into RNA (T to U).")]
+    Transcribe {
+        #[arg(help = "DNA sequence")]
+        seqs: Vec<String>,
+    },
+    #[command(about = "Converts RNA back into DNA (U to T).")]
+    BackTranscribe {
+        #[arg(help = "RNA sequence")]
+        seqs: Vec<String>,
     },
     #[command(about = "Computes the length of a sequence.")]
     Length {
         #[arg(help = "DNA/RNA/protein sequence")]
         seq: Vec<String>,
+        #[arg(long, help = "Also report the lengths of soft-masked (lowercase) and unmasked regions")]
+        masked: bool,
+    },
+    #[command(
+        about = "Computes the molecular weight and extinction coefficient of a nucleic acid, and converts between concentration units."
+    )]
+    #[command(group(ArgGroup::new("concentration").args(["ng_per_ul", "nm", "copies_per_ul", "a260"])))]
+    Mw {
+        #[arg(help = "DNA/RNA sequence")]
+        seqs: Vec<String>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Type of nucleic acid",
+            default_value = "dsdna"
+        )]
+        molecule: molecular_weight::Molecule,
+        #[arg(
+            long,
+            conflicts_with = "seqs",
+            help = "Use the average molecular weight of a sequence of this length instead of an actual sequence"
+        )]
+        length: Option<usize>,
+        #[arg(long, help = "Convert from a concentration in ng/µL")]
+        ng_per_ul: Option<f64>,
+        #[arg(long, help = "Convert from a concentration in nM")]
+        nm: Option<f64>,
+        #[arg(long, help = "Convert from a concentration in copies/µL")]
+        copies_per_ul: Option<f64>,
+        #[arg(long, help = "Convert from an absorbance at 260 nm (1 cm path length)")]
+        a260: Option<f64>,
     },
     #[command(about = "Computes the GC content of a nucleic acid sequence.")]
     GCContent {
         #[arg(help = "RNA/DNA sequence")]
         seqs: Vec<String>,
+        #[arg(long, help = "Also report the GC content of soft-masked (lowercase) and unmasked regions")]
+        masked: bool,
+        #[arg(
+            long,
+            conflicts_with = "masked",
+            help = "Report GC content and GC skew in sliding windows of this size"
+        )]
+        window: Option<usize>,
+        #[arg(
+            long,
+            requires = "window",
+            help = "Distance between the starts of consecutive windows [default: the window size]"
+        )]
+        step: Option<usize>,
+    },
+    #[command(about = "Reports the base and dinucleotide composition of a nucleic acid sequence.")]
+    Composition {
+        #[arg(help = "RNA/DNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Report the composition of each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+    },
+    #[command(
+        visible_alias = "dust",
+        about = "Computes sequence complexity (Shannon entropy and DUST score) and masks low-complexity regions."
+    )]
+    Complexity {
+        #[arg(help = "DNA/RNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Process each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(long, help = "Window size", default_value_t = 64)]
+        window: usize,
+        #[arg(
+            long,
+            help = "Distance between the starts of consecutive windows [default: the window size]"
+        )]
+        step: Option<usize>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Output the sequence with low-complexity regions masked instead of a track of scores"
+        )]
+        mask: Option<complexity::MaskStyle>,
+        #[arg(
+            long,
+            help = "Windows with a DUST score above this are low-complexity",
+            default_value_t = 20.0
+        )]
+        dust_threshold: f64,
+        #[arg(long, help = "Windows with a Shannon entropy (in bits) below this are also low-complexity")]
+        min_entropy: Option<f64>,
+    },
+    #[command(about = "Finds homopolymers, microsatellites and tandem repeats.")]
+    Repeats {
+        #[arg(help = "DNA/RNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Process each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(long, help = "Minimum length of reported homopolymers", default_value_t = 6)]
+        min_homopolymer: usize,
+        #[arg(
+            long,
+            help = "Minimum length of reported microsatellites (periods 2-6)",
+            default_value_t = 12
+        )]
+        min_microsatellite: usize,
+        #[arg(
+            long,
+            help = "Minimum length of reported tandem repeats (periods above 6)",
+            default_value_t = 20
+        )]
+        min_tandem: usize,
+        #[arg(long, help = "Maximum repeat unit length", default_value_t = 50)]
+        max_period: usize,
+        #[arg(long, help = "Treat the sequence as circular (e.g. a plasmid)")]
+        circular: bool,
+        #[arg(long, help = "Use zero-based coordinates")]
+        use_0_based_coords: bool,
+    },
+    #[command(about = "Reports codon usage and the Codon Adaptation Index of a coding sequence.")]
+    CodonUsage {
+        #[arg(help = "Coding DNA/RNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Process each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Reference organism for the CAI",
+            default_value = "ecoli"
+        )]
+        organism: codons::Organism,
+        #[arg(
+            long,
+            conflicts_with = "organism",
+            help = "Reference codon usage table to use instead of a bundled organism"
+        )]
+        table: Option<PathBuf>,
+    },
+    #[command(
+        about = "Designs a DNA sequence encoding a protein, using an organism's preferred codons."
+    )]
+    BackTranslate {
+        #[arg(help = "Protein sequence (use * for stop codons)")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Process each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Organism whose codon usage is used",
+            default_value = "ecoli"
+        )]
+        organism: codons::Organism,
+        #[arg(
+            long,
+            conflicts_with = "organism",
+            help = "Codon usage table to use instead of a bundled organism"
+        )]
+        table: Option<PathBuf>,
+        #[arg(long, help = "Minimum GC content", default_value_t = 0.25)]
+        min_gc: f64,
+        #[arg(long, help = "Maximum GC content", default_value_t = 0.65)]
+        max_gc: f64,
+        #[arg(long, help = "Longest allowed homopolymer", default_value_t = 5)]
+        max_homopolymer: usize,
+        #[arg(
+            long,
+            help = "Never use codons whose usage relative to the organism's preferred synonymous codon is below this",
+            default_value_t = 0.1
+        )]
+        min_codon_weight: f64,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            help = "Comma-separated list of enzymes whose recognition sites must be avoided"
+        )]
+        forbidden_enzymes: Vec<String>,
+    },
+    #[command(
+        about = "Computes physicochemical properties of a protein (molecular weight, pI, extinction coefficient, etc.)."
+    )]
+    Protparam {
+        #[arg(help = "Protein sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Process each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+    },
+    #[command(about = "Finds occurrences of a motif on both strands.")]
+    Find {
+        #[arg(help = "Motif, which may contain IUPAC degenerate bases")]
+        pattern: String,
+        #[arg(help = "DNA/RNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Search each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Treat the motif as a (case-insensitive) regular expression"
+        )]
+        regex: bool,
+        #[arg(
+            short,
+            long,
+            conflicts_with = "regex",
+            help = "Maximum number of mismatches",
+            default_value_t = 0
+        )]
+        mismatches: usize,
+        #[arg(long, help = "Treat the sequence as circular (e.g. a plasmid)")]
+        circular: bool,
+        #[arg(long, help = "Use zero-based coordinates")]
+        use_0_based_coords: bool,
+    },
+    #[command(about = "Scans sequences for matches to position weight matrices on both strands.")]
+    PwmScan {
+        #[arg(
+            short,
+            long,
+            help = "File of motif matrices in JASPAR, MEME or TRANSFAC format"
+        )]
+        matrix: PathBuf,
+        #[arg(help = "DNA/RNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Scan each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Report matches with at most this p-value",
+            default_value_t = 1e-4
+        )]
+        p_value: f64,
+        #[arg(
+            long,
+            help = "Pseudocount added to the counts at each position",
+            default_value_t = 1.0
+        )]
+        pseudocount: f64,
+        #[arg(
+            long,
+            help = "Report the score threshold of each motif for the p-value instead of scanning"
+        )]
+        show_thresholds: bool,
+        #[arg(long, help = "Use zero-based coordinates")]
+        use_0_based_coords: bool,
+    },
+    #[command(about = "Finds CRISPR guide protospacers next to a PAM on both strands.")]
+    Guides {
+        #[arg(help = "DNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Search each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Nuclease, which sets the default PAM, its side and the protospacer length",
+            default_value = "cas9"
+        )]
+        nuclease: guides::Nuclease,
+        #[arg(
+            long,
+            help = "PAM to use instead, which may contain IUPAC degenerate bases"
+        )]
+        pam: Option<String>,
+        #[arg(long, help = "Protospacer length to use instead")]
+        length: Option<usize>,
+        #[arg(
+            long,
+            help = "Flag protospacers with longer homopolymers",
+            default_value_t = 4
+        )]
+        max_homopolymer: usize,
+        #[arg(long, help = "Treat the sequence as circular (e.g. a plasmid)")]
+        circular: bool,
+        #[arg(long, help = "Use zero-based coordinates")]
+        use_0_based_coords: bool,
+    },
+    #[command(
+        about = "Finds sites in a reference that nearly match a guide or probe, allowing mismatches and bulges."
+    )]
+    OffTargets {
+        #[arg(help = "Guide or probe sequence, which may contain IUPAC degenerate bases")]
+        probe: String,
+        #[arg(help = "FASTA file of reference sequences")]
+        reference: PathBuf,
+        #[arg(
+            short,
+            long,
+            help = "Maximum number of mismatches",
+            default_value_t = 3
+        )]
+        mismatches: usize,
+        #[arg(
+            long,
+            help = "Maximum number of bulges (extra or missing bases in the reference)",
+            default_value_t = 0
+        )]
+        bulges: usize,
+        #[arg(long, help = "Hide start/end coordinates of aligned segments")]
+        hide_coords: bool,
+        #[arg(
+            long,
+            help = "Maximum width of aligned characters",
+            default_value_t = 60
+        )]
+        line_width: usize,
+        #[arg(long, help = "Use zero-based coordinates")]
+        use_0_based_coords: bool,
+    },
+    #[command(about = "Computes the Hamming distance between sequences of the same length.")]
+    Hamming {
+        #[arg(help = "Two or more sequences")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Compare the records in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(long, help = "Compare sequences case-insensitively")]
+        ignore_case: bool,
+        #[arg(
+            long,
+            help = "Also compare with the reverse complement of the second sequence of each pair, and report the smaller distance"
+        )]
+        ignore_strand: bool,
+    },
+    #[command(about = "Computes the edit (Levenshtein) distance between sequences.")]
+    EditDistance {
+        #[arg(help = "Two or more sequences")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Compare the records in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(long, help = "Compare sequences case-insensitively")]
+        ignore_case: bool,
+        #[arg(
+            long,
+            help = "Also compare with the reverse complement of the second sequence of each pair, and report the smaller distance"
+        )]
+        ignore_strand: bool,
+    },
+    #[command(about = "Validates a set of sample barcodes, or generates one.")]
+    Barcodes {
+        #[arg(
+            help = "Barcodes to validate, or existing barcodes that generated ones must be compatible with"
+        )]
+        seqs: Vec<String>,
+        #[arg(long, help = "Read barcodes from a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(
+            long,
+            value_enum,
+            help = "Distance between barcodes",
+            default_value = "hamming"
+        )]
+        metric: distance::Metric,
+        #[arg(
+            long,
+            help = "Minimum distance between any two barcodes",
+            default_value_t = 3
+        )]
+        min_distance: usize,
+        #[arg(
+            long,
+            help = "Minimum GC content of each barcode",
+            default_value_t = 0.25
+        )]
+        min_gc: f64,
+        #[arg(
+            long,
+            help = "Maximum GC content of each barcode",
+            default_value_t = 0.75
+        )]
+        max_gc: f64,
+        #[arg(long, help = "Maximum homopolymer length", default_value_t = 2)]
+        max_homopolymer: usize,
+        #[arg(long, help = "Generate this many new barcodes instead of validating")]
+        generate: Option<usize>,
+        #[arg(
+            long,
+            requires = "generate",
+            help = "Length of generated barcodes",
+            default_value_t = 8
+        )]
+        length: usize,
+        #[arg(
+            long,
+            requires = "generate",
+            help = "Random seed, for generating the same barcodes again"
+        )]
+        seed: Option<u64>,
+    },
+    #[command(about = "Splits FASTQ reads into one file per sample by their barcodes.")]
+    Demux {
+        #[arg(help = "FASTQ file of reads")]
+        reads: PathBuf,
+        #[arg(short, long, help = "FASTA file of sample barcodes, named by sample")]
+        barcodes: PathBuf,
+        #[arg(short, long, help = "Directory to write the per-sample FASTQ files to")]
+        out_dir: PathBuf,
+        #[arg(
+            long,
+            value_enum,
+            help = "Where the barcode is found in each read",
+            default_value = "inline"
+        )]
+        location: demux::BarcodeLocation,
+        #[arg(
+            short,
+            long,
+            help = "Maximum number of mismatches in the barcode",
+            default_value_t = 1
+        )]
+        mismatches: usize,
+        #[arg(long, help = "Don't remove inline barcodes from the reads")]
+        keep_barcode: bool,
+    },
+    #[command(about = "Trims adapters and low-quality bases from reads.")]
+    Trim {
+        #[arg(help = "FASTQ or FASTA file of reads")]
+        reads: PathBuf,
+        #[arg(
+            short,
+            long = "adapter",
+            help = "3' adapter to remove, which may contain IUPAC degenerate bases (can be given more than once)"
+        )]
+        adapters: Vec<String>,
+        #[arg(
+            short,
+            long,
+            help = "Maximum fraction of errors in the part of the adapter that overlaps the read",
+            default_value_t = 0.1
+        )]
+        error_rate: f64,
+        #[arg(
+            long,
+            help = "Minimum overlap between the adapter and the read",
+            default_value_t = 3
+        )]
+        min_overlap: usize,
+        #[arg(
+            short,
+            long,
+            help = "Trim bases from the 3' end of FASTQ reads with qualities below this"
+        )]
+        quality_cutoff: Option<u8>,
+        #[arg(
+            long,
+            help = "Discard reads shorter than this after trimming",
+            default_value_t = 0
+        )]
+        min_length: usize,
+    },
+    #[command(about = "Flags features that are likely to make gene synthesis fail.")]
+    SynthCheck {
+        #[arg(help = "DNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Check each record in a FASTA file instead")]
+        fasta: Option<PathBuf>,
+        #[arg(long, help = "Minimum overall GC content", default_value_t = 0.25)]
+        min_gc: f64,
+        #[arg(long, help = "Maximum overall GC content", default_value_t = 0.65)]
+        max_gc: f64,
+        #[arg(long, help = "Window size for local GC content", default_value_t = 50)]
+        window: usize,
+        #[arg(long, help = "Minimum GC content of any window", default_value_t = 0.2)]
+        min_window_gc: f64,
+        #[arg(long, help = "Maximum GC content of any window", default_value_t = 0.8)]
+        max_window_gc: f64,
+        #[arg(long, help = "Longest allowed homopolymer", default_value_t = 8)]
+        max_homopolymer: usize,
+        #[arg(
+            long,
+            help = "Longest allowed microsatellite or tandem repeat",
+            default_value_t = 20
+        )]
+        max_repeat: usize,
+        #[arg(
+            long,
+            help = "Minimum stem length of reported hairpins",
+            default_value_t = 10
+        )]
+        min_hairpin_stem: usize,
+        #[arg(
+            long,
+            help = "Maximum loop length of reported hairpins",
+            default_value_t = 50
+        )]
+        max_hairpin_loop: usize,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            help = "Comma-separated list of enzymes whose recognition sites are not allowed"
+        )]
+        forbidden_enzymes: Vec<String>,
+        #[arg(long, help = "Use zero-based coordinates")]
+        use_0_based_coords: bool,
     },
     #[command(about = "Performs a local pairwise alignment of two sequences.")]
     PairwiseLocal {
@@ -49,6 +595,11 @@ enum Commands {
         line_width: usize,
         #[arg(long, help = "Use zero-based coordinates")]
         use_0_based_coords: bool,
+        #[arg(
+            long,
+            help = "Treat the second sequence as circular, allowing the alignment to wrap around its origin"
+        )]
+        circular: bool,
     },
     #[command(about = "Performs a semiglobal pairwise alignment of two sequences.")]
     PairwiseSemiglobal {
@@ -69,6 +620,11 @@ enum Commands {
         line_width: usize,
         #[arg(long, help = "Use zero-based coordinates")]
         use_0_based_coords: bool,
+        #[arg(
+            long,
+            help = "Treat the second sequence as circular, allowing the alignment to wrap around its origin"
+        )]
+        circular: bool,
     },
     #[command(about = "Performs a global pairwise alignment of two sequences.")]
     PairwiseGlobal {
@@ -89,6 +645,76 @@ enum Commands {
         line_width: usize,
         #[arg(long, help = "Use zero-based coordinates")]
         use_0_based_coords: bool,
+        #[arg(
+            long,
+            help = "Treat the second sequence as circular, allowing the alignment to wrap around its origin"
+        )]
+        circular: bool,
+    },
+    #[command(about = "Extracts a subsequence by its coordinates.")]
+    Slice {
+        #[arg(help = "DNA/RNA/protein sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Start of the region (one-based and inclusive, unless --use-0-based-coords is given)")]
+        start: usize,
+        #[arg(long, help = "End of the region (inclusive, unless --use-0-based-coords is given)")]
+        end: usize,
+        #[arg(long, help = "Use zero-based, half-open coordinates")]
+        use_0_based_coords: bool,
+        #[arg(long, help = "Return the reverse complement of the region")]
+        reverse_strand: bool,
+        #[arg(
+            long,
+            help = "Treat the sequence as circular, so a start after the end wraps around the origin"
+        )]
+        circular: bool,
+    },
+    #[command(about = "Rotates a circular sequence to begin at a position or motif.")]
+    #[command(group(ArgGroup::new("rotation").required(true).args(["position", "motif", "canonical"])))]
+    Rotate {
+        #[arg(help = "DNA sequence")]
+        seqs: Vec<String>,
+        #[arg(long, help = "Position (one-based) that the rotated sequence should begin with")]
+        position: Option<usize>,
+        #[arg(
+            long,
+            help = "Motif that the rotated sequence should begin with. If it only occurs on the reverse strand, the reverse complement is rotated."
+        )]
+        motif: Option<String>,
+        #[arg(
+            long,
+            help = "Rotate to the lexicographically smallest rotation of either strand, so that identical circular sequences compare equal"
+        )]
+        canonical: bool,
+    },
+    #[command(about = "Finds restriction enzyme sites and performs a virtual digest.")]
+    Digest {
+        #[arg(help = "DNA sequence")]
+        seqs: Vec<String>,
+        #[arg(
+            short,
+            long,
+            value_delimiter = ',',
+            help = "Comma-separated list of enzymes. If omitted, lists every enzyme that cuts the sequence."
+        )]
+        enzymes: Vec<String>,
+        #[arg(long, help = "Treat the sequence as circular (e.g. a plasmid)")]
+        circular: bool,
+        #[arg(long, help = "Use zero-based coordinates")]
+        use_0_based_coords: bool,
+        #[arg(long, help = "List all available enzymes and their recognition sites")]
+        list_enzymes: bool,
+    },
+    #[command(about = "Simulates an agarose gel of fragments of the given sizes.")]
+    Gel {
+        #[arg(help = "Comma-separated fragment sizes (in bp) for each lane")]
+        lanes: Vec<String>,
+        #[arg(long, value_enum, help = "Size standard in the first lane", default_value = "1kb")]
+        ladder: gel::Ladder,
+        #[arg(long, help = "Agarose percentage", default_value_t = 1.0)]
+        percent: f64,
+        #[arg(long, help = "Render the gel as SVG instead of text")]
+        svg: bool,
     },
 }
 
@@ -105,29 +731,174 @@ struct DisplayOptions {
     try_rc: bool,
     line_width: usize,
     use_0_based_coords: bool,
+    circular: bool,
+}
+
+/// Determines whether a sequence is RNA, either because it contains uracil or because the user
+/// said so. Sequences that contain both thymine and uracil are rejected.
+fn is_rna(seqs: &[String], force_rna: bool) -> Result<bool> {
+    let seq = seqs.join("").replace(" ", "").replace("-", "");
+    let first_t = seq.chars().position(|c| matches!(c, 'T' | 't'));
+    let first_u = seq.chars().position(|c| matches!(c, 'U' | 'u'));
+    match (first_t, first_u) {
+        (Some(t), Some(u)) => {
+            let i = cmp::max(t, u);
+            let c = seq.chars().nth(i).unwrap();
+            Err(anyhow!("Mixed DNA/RNA base: '{c}' at position {i}"))
+        }
+        (Some(i), None) if force_rna => {
+            let c = seq.chars().nth(i).unwrap();
+            Err(anyhow!("DNA base in RNA sequence: '{c}' at position {i}"))
+        }
+        (_, first_u) => Ok(force_rna || first_u.is_some()),
+    }
 }
 
-fn build_reverse_complement(seqs: Vec<String>) -> Result<String> {
+fn build_reverse_complement(seqs: Vec<String>, force_rna: bool) -> Result<String> {
+    let rna = is_rna(&seqs, force_rna)?;
     let reversed_complements: Vec<String> = seqs
         .into_iter()
         .rev()
         .map(|sequence| {
             let seq = sequence.into_bytes();
-            String::from_utf8(revcomp(seq)).context("Failed to build reverse complement")
+            let rc = if rna { rna_revcomp(seq) } else { revcomp(seq) };
+            String::from_utf8(rc).context("Failed to build reverse complement")
         })
         .collect::<Result<Vec<_>, anyhow::Error>>()?;
     Ok(reversed_complements.join(" "))
 }
 
-fn get_seq_length(seqs: Vec<String>) -> Result<String> {
-    Ok(seqs
-        .join("")
+fn build_complement(seqs: Vec<String>, force_rna: bool) -> Result<String> {
+    let rna = is_rna(&seqs, force_rna)?;
+    let complements: Vec<String> = seqs
+        .into_iter()
+        .map(|sequence| {
+            let seq: Vec<u8> = sequence
+                .into_bytes()
+                .into_iter()
+                .map(|base| if rna { rna_complement(base) } else { complement(base) })
+                .collect();
+            String::from_utf8(seq).context("Failed to build complement")
+        })
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    Ok(complements.join(" "))
+}
+
+fn build_reverse(seqs: Vec<String>) -> Result<String> {
+    let reversed: Vec<String> = seqs
+        .into_iter()
+        .rev()
+        .map(|sequence| sequence.chars().rev().collect())
+        .collect();
+    Ok(reversed.join(" "))
+}
+
+fn transcribe(seqs: Vec<String>) -> Result<String> {
+    Ok(seqs.join(" ").replace('T', "U").replace('t', "u"))
+}
+
+fn back_transcribe(seqs: Vec<String>) -> Result<String> {
+    Ok(seqs.join(" ").replace('U', "T").replace('u', "t"))
+}
+
+fn build_circular_reverse_complement(
+    seqs: Vec<String>,
+    origin: Option<usize>,
+    force_rna: bool,
+) -> Result<String> {
+    let rna = is_rna(&seqs, force_rna)?;
+    let seq = seqs.join("").replace(" ", "").replace("-", "");
+    let start = match origin {
+        Some(origin) => circular::origin_to_start(origin, seq.len())?,
+        None => 0,
+    };
+    let rotated = circular::rotate(seq.as_bytes(), start);
+    let rc = if rna {
+        rna_revcomp(rotated)
+    } else {
+        revcomp(rotated)
+    };
+    String::from_utf8(rc).context("Failed to build reverse complement")
+}
+
+fn slice_sequence(
+    seqs: Vec<String>,
+    start: usize,
+    end: usize,
+    use_0_based_coords: bool,
+    reverse_strand: bool,
+    circular: bool,
+) -> Result<String> {
+    let seq = seqs.join("").replace(" ", "").replace("-", "");
+    if !seq.is_ascii() {
+        bail!("Sequence contains non-ASCII characters");
+    }
+    let len = seq.len();
+    // Whether the region wraps around the origin depends on the coordinates as given, since a
+    // one-based start just past the end would otherwise look like an empty region.
+    let wraps = start > end;
+    let (given_start, given_end) = (start, end);
+    // Internally, we always work with zero-based, half-open coordinates.
+    let start = if use_0_based_coords {
+        start
+    } else {
+        start
+            .checked_sub(1)
+            .ok_or_else(|| anyhow!("Coordinates are one-based, so the start must be at least 1"))?
+    };
+    // A region that wraps around the origin has to start at a base that exists.
+    if start > len || end > len || (wraps && start == len) {
+        bail!("Coordinates {given_start}-{given_end} are outside of the sequence (length {len})");
+    }
+    let region = if !wraps {
+        seq[start..end].to_string()
+    } else if circular {
+        format!("{}{}", &seq[start..], &seq[..end])
+    } else {
+        bail!("Start is after end. Use --circular to extract a region that spans the origin.");
+    };
+    if reverse_strand {
+        // Judged on the whole sequence, since the region itself may have no U or T.
+        let rc = if is_rna(std::slice::from_ref(&seq), false)? {
+            rna_revcomp(region.into_bytes())
+        } else {
+            revcomp(region.into_bytes())
+        };
+        String::from_utf8(rc).context("Failed to build reverse complement")
+    } else {
+        Ok(region)
+    }
+}
+
+fn compute_seq_length(seqs: &[String]) -> usize {
+    seqs.join("")
         .chars()
         .filter(|ch| *ch != '-')
         .filter(|ch| *ch != ' ')
-        .collect::<Vec<_>>()
-        .len()
-        .to_string())
+        .count()
+}
+
+fn get_seq_length(seqs: Vec<String>) -> Result<String> {
+    Ok(compute_seq_length(&seqs).to_string())
+}
+
+/// Splits a sequence into its soft-masked (lowercase) and unmasked (uppercase) bases, ignoring
+/// spaces and dashes.
+fn split_masked(seqs: &[String]) -> (String, String) {
+    seqs.join("")
+        .chars()
+        .filter(|ch| *ch != '-' && *ch != ' ')
+        .partition(|ch| ch.is_lowercase())
+}
+
+fn get_masked_seq_length(seqs: Vec<String>) -> Result<String> {
+    let (masked, unmasked) = split_masked(&seqs);
+    Ok(format!(
+        "total\t{}\nmasked\t{}\nunmasked\t{}",
+        masked.len() + unmasked.len(),
+        masked.len(),
+        unmasked.len()
+    ))
 }
 
 fn confirm_valid_nucleic_acid(seq: &str) -> Result<()> {
@@ -136,18 +907,117 @@ fn confirm_valid_nucleic_acid(seq: &str) -> Result<()> {
             return Err(anyhow!("Invalid/ambiguous base: '{c}' at position {i}"));
         }
     }
-    Ok(())
+    Ok(())
+}
+
+/// Whether a base is a G or a C, as counts of each, so that GC content and GC skew classify
+/// bases the same way.
+fn gc_base_counts(base: u8) -> (usize, usize) {
+    match base {
+        b'G' | b'g' => (1, 0),
+        b'C' | b'c' => (0, 1),
+        _ => (0, 0),
+    }
+}
+
+fn compute_gc_content(seqs: Vec<String>) -> Result<f32> {
+    let seq = seqs.join("").replace(" ", "").replace("-", "");
+    confirm_valid_nucleic_acid(&seq)?;
+    let (g, c) = seq
+        .bytes()
+        .map(gc_base_counts)
+        .fold((0, 0), |(g, c), (dg, dc)| (g + dg, c + dc));
+    Ok((g + c) as f32 / seq.len() as f32)
+}
+
+fn gc_content(seqs: Vec<String>) -> Result<String> {
+    let gc = compute_gc_content(seqs)?;
+    Ok(format!("{:.16}", gc))
+}
+
+fn masked_gc_content(seqs: Vec<String>) -> Result<String> {
+    let total = gc_content(seqs.clone())?;
+    let (masked, unmasked) = split_masked(&seqs);
+    let format_part = |part: String| -> Result<String> {
+        if part.is_empty() {
+            Ok("n/a".to_string())
+        } else {
+            gc_content(vec![part])
+        }
+    };
+    Ok(format!(
+        "total\t{}\nmasked\t{}\nunmasked\t{}",
+        total,
+        format_part(masked)?,
+        format_part(unmasked)?
+    ))
+}
+
+struct GcWindow {
+    start: usize,
+    end: usize,
+    g: usize,
+    c: usize,
+}
+
+impl GcWindow {
+    fn gc_content(&self) -> f64 {
+        (self.g + self.c) as f64 / (self.end - self.start) as f64
+    }
 }
 
-fn compute_gc_content(seqs: Vec<String>) -> Result<f32> {
-    let seq = seqs.join("").replace(" ", "").replace("-", "");
-    confirm_valid_nucleic_acid(&seq)?;
-    Ok(rustbio_gc_content(seq.as_bytes()))
+/// G and C counts in sliding windows, using zero-based, half-open coordinates. Only complete
+/// windows are returned.
+fn compute_windowed_gc_content(seq: &str, window: usize, step: usize) -> Result<Vec<GcWindow>> {
+    if window == 0 || step == 0 {
+        bail!("Window and step sizes must be greater than zero");
+    }
+    confirm_valid_nucleic_acid(seq)?;
+    if window > seq.len() {
+        bail!(
+            "Window size ({window}) is larger than the sequence (length {})",
+            seq.len()
+        );
+    }
+    // Running totals of G and C, so each window's counts can be found in constant time.
+    let mut g_totals = vec![0];
+    let mut c_totals = vec![0];
+    for base in seq.bytes() {
+        let (g, c) = gc_base_counts(base);
+        g_totals.push(g_totals[g_totals.len() - 1] + g);
+        c_totals.push(c_totals[c_totals.len() - 1] + c);
+    }
+    Ok((0..=seq.len() - window)
+        .step_by(step)
+        .map(|start| {
+            let end = start + window;
+            GcWindow {
+                start,
+                end,
+                g: g_totals[end] - g_totals[start],
+                c: c_totals[end] - c_totals[start],
+            }
+        })
+        .collect())
 }
 
-fn gc_content(seqs: Vec<String>) -> Result<String> {
-    let gc = compute_gc_content(seqs)?;
-    Ok(format!("{:.16}", gc))
+/// GC content and GC skew in sliding windows, as a BED-like track with zero-based, half-open
+/// coordinates.
+fn windowed_gc_content(seqs: Vec<String>, window: usize, step: usize) -> Result<String> {
+    let seq = seqs.join("").replace(" ", "").replace("-", "");
+    let output: Vec<String> = compute_windowed_gc_content(&seq, window, step)?
+        .iter()
+        .map(|w| {
+            format!(
+                "{}\t{}\t{:.4}\t{}",
+                w.start,
+                w.end,
+                w.gc_content(),
+                composition::format_optional(composition::skew(w.g, w.c))
+            )
+        })
+        .collect();
+    Ok(output.join("\n"))
 }
 
 struct AlignmentDisplayLine {
@@ -216,7 +1086,13 @@ fn make_display_lines(
                     b_alignment.push_str(b_char);
                     b_index += 1;
 
-                    alignment_string.push('.');
+                    // rust-bio compares bases exactly, but soft-masked (lowercase) bases still
+                    // match their uppercase counterparts.
+                    if a_char.eq_ignore_ascii_case(b_char) {
+                        alignment_string.push('|');
+                    } else {
+                        alignment_string.push('.');
+                    }
                 }
                 AlignmentOperation::Xclip(n) => {
                     for _ in 0..*n {
@@ -263,6 +1139,48 @@ fn run_alignment(
     }
 }
 
+/// Aligns `a_bytes` to `b_bytes`. If the second sequence is circular, the alignment is allowed
+/// to wrap around its origin. Returns the alignment along with the sequence it was actually made
+/// against and the position in `b_bytes` where that sequence starts.
+fn align_to_target(
+    alignment_command: &AlignmentCommand,
+    aligner: &mut Aligner<impl Fn(u8, u8) -> i32>,
+    a_bytes: &[u8],
+    b_bytes: &[u8],
+    circular: bool,
+) -> (Alignment, Vec<u8>, usize) {
+    if !circular || b_bytes.is_empty() {
+        let alignment = run_alignment(alignment_command, aligner, a_bytes, b_bytes);
+        return (alignment, b_bytes.to_vec(), 0);
+    }
+    let overlap = cmp::min(a_bytes.len(), b_bytes.len()).saturating_sub(1);
+    let extended = circular::extend_across_origin(b_bytes, overlap);
+    match alignment_command {
+        AlignmentCommand::Global => {
+            // A global alignment has to begin at the start of the second sequence, so we first
+            // find where the best wrapped alignment starts and rotate the sequence to begin there.
+            let wrapped = aligner.semiglobal(a_bytes, &extended);
+            let offset = wrapped.ystart % b_bytes.len();
+            let rotated = circular::rotate(b_bytes, offset);
+            let alignment = aligner.global(a_bytes, &rotated);
+            (alignment, rotated, offset)
+        }
+        _ => {
+            let alignment = run_alignment(alignment_command, aligner, a_bytes, &extended);
+            (alignment, extended, 0)
+        }
+    }
+}
+
+/// Converts coordinates in the second sequence from positions in the (extended or rotated)
+/// alignment target back to positions in the original circular sequence.
+fn wrap_b_coordinates(display_lines: &mut [AlignmentDisplayLine], b_offset: usize, b_len: usize) {
+    for line in display_lines {
+        line.b_start = (line.b_start + b_offset) % b_len;
+        line.b_end = (line.b_end + b_offset + b_len - 1) % b_len + 1;
+    }
+}
+
 fn pairwise(
     alignment_command: AlignmentCommand,
     seqs: Vec<String>,
@@ -281,7 +1199,7 @@ fn pairwise(
     let a_bytes = a.as_bytes();
     let b_bytes = b.as_bytes();
     let score = |a: u8, b: u8| {
-        if a.to_ascii_uppercase() == b.to_ascii_uppercase() {
+        if a.eq_ignore_ascii_case(&b) {
             1i32
         } else {
             -1i32
@@ -290,21 +1208,37 @@ fn pairwise(
 
     let mut aligner =
         Aligner::with_capacity(a.len(), b.len(), gap_open_score, gap_extend_score, &score);
-    let alignment = run_alignment(&alignment_command, &mut aligner, a_bytes, b_bytes);
-    let (alignment, a, a_is_rc) = if opts.try_rc {
+    let (alignment, b_target, b_offset) = align_to_target(
+        &alignment_command,
+        &mut aligner,
+        a_bytes,
+        b_bytes,
+        opts.circular,
+    );
+    let (alignment, a, a_is_rc, b_target, b_offset) = if opts.try_rc {
         let a_rc_bytes = revcomp(a.as_bytes());
         let a_rc = String::from_utf8(a_rc_bytes.clone())?;
-        let alignment_rc = run_alignment(&alignment_command, &mut aligner, &a_rc_bytes, b_bytes);
+        let (alignment_rc, b_target_rc, b_offset_rc) = align_to_target(
+            &alignment_command,
+            &mut aligner,
+            &a_rc_bytes,
+            b_bytes,
+            opts.circular,
+        );
         if alignment.score >= alignment_rc.score {
-            (alignment, a, false)
+            (alignment, a, false, b_target, b_offset)
         } else {
-            (alignment_rc, a_rc, true)
+            (alignment_rc, a_rc, true, b_target_rc, b_offset_rc)
         }
     } else {
-        (alignment, a, false)
+        (alignment, a, false, b_target, b_offset)
     };
 
-    let (display_lines, a_end) = make_display_lines(alignment, a, b, opts.line_width);
+    let b_target = String::from_utf8(b_target)?;
+    let (mut display_lines, a_end) = make_display_lines(alignment, a, b_target, opts.line_width);
+    if opts.circular {
+        wrap_b_coordinates(&mut display_lines, b_offset, b.len());
+    }
     let pretty_alignment = format_display_lines(
         &display_lines,
         opts.hide_coords,
@@ -417,9 +1351,319 @@ fn main() -> Result<()> {
     let args = Args::parse();
 
     let output = match args.command {
-        Commands::ReverseComplement { seqs } => build_reverse_complement(seqs),
-        Commands::Length { seq } => get_seq_length(seq),
-        Commands::GCContent { seqs } => gc_content(seqs),
+        Commands::ReverseComplement {
+            seqs,
+            rna,
+            circular,
+            origin,
+        } => {
+            if circular {
+                build_circular_reverse_complement(seqs, origin, rna)
+            } else {
+                build_reverse_complement(seqs, rna)
+            }
+        }
+        Commands::Complement { seqs, rna } => build_complement(seqs, rna),
+        Commands::Reverse { seqs } => build_reverse(seqs),
+        Commands::Transcribe { seqs } => transcribe(seqs),
+        Commands::BackTranscribe { seqs } => back_transcribe(seqs),
+        Commands::Length { seq, masked } => {
+            if masked {
+                get_masked_seq_length(seq)
+            } else {
+                get_seq_length(seq)
+            }
+        }
+        Commands::Mw {
+            seqs,
+            molecule,
+            length,
+            ng_per_ul,
+            nm,
+            copies_per_ul,
+            a260,
+        } => {
+            let concentration = ng_per_ul
+                .map(molecular_weight::Concentration::NgPerUl)
+                .or(nm.map(molecular_weight::Concentration::Nanomolar))
+                .or(copies_per_ul.map(molecular_weight::Concentration::CopiesPerUl))
+                .or(a260.map(molecular_weight::Concentration::A260));
+            molecular_weight::mw(seqs, molecule, length, concentration)
+        }
+        Commands::GCContent {
+            seqs,
+            masked,
+            window,
+            step,
+        } => {
+            if let Some(window) = window {
+                windowed_gc_content(seqs, window, step.unwrap_or(window))
+            } else if masked {
+                masked_gc_content(seqs)
+            } else {
+                gc_content(seqs)
+            }
+        }
+        Commands::Composition { seqs, fasta } => {
+            composition::composition(seqs, fasta.as_deref())
+        }
+        Commands::Complexity {
+            seqs,
+            fasta,
+            window,
+            step,
+            mask,
+            dust_threshold,
+            min_entropy,
+        } => {
+            let opts = complexity::ComplexityOptions {
+                window,
+                step: step.unwrap_or(window),
+                thresholds: complexity::Thresholds {
+                    dust: dust_threshold,
+                    min_entropy,
+                },
+                mask,
+            };
+            complexity::complexity(seqs, fasta.as_deref(), opts)
+        }
+        Commands::Repeats {
+            seqs,
+            fasta,
+            min_homopolymer,
+            min_microsatellite,
+            min_tandem,
+            max_period,
+            circular,
+            use_0_based_coords,
+        } => {
+            let opts = repeats::RepeatOptions {
+                min_homopolymer,
+                min_microsatellite,
+                min_tandem,
+                max_period,
+                circular,
+            };
+            repeats::repeats(seqs, fasta.as_deref(), opts, use_0_based_coords)
+        }
+        Commands::CodonUsage {
+            seqs,
+            fasta,
+            organism,
+            table,
+        } => codons::codon_usage(seqs, fasta.as_deref(), organism, table.as_deref()),
+        Commands::BackTranslate {
+            seqs,
+            fasta,
+            organism,
+            table,
+            min_gc,
+            max_gc,
+            max_homopolymer,
+            min_codon_weight,
+            forbidden_enzymes,
+        } => {
+            let opts = backtranslate::BackTranslateOptions {
+                min_gc,
+                max_gc,
+                max_homopolymer,
+                min_codon_weight,
+                forbidden_enzymes,
+            };
+            backtranslate::back_translate(seqs, fasta.as_deref(), organism, table.as_deref(), opts)
+        }
+        Commands::Protparam { seqs, fasta } => protein::protparam(seqs, fasta.as_deref()),
+        Commands::Find {
+            pattern,
+            seqs,
+            fasta,
+            regex,
+            mismatches,
+            circular,
+            use_0_based_coords,
+        } => {
+            let pattern = if regex {
+                search::Pattern::Regex(pattern)
+            } else {
+                search::Pattern::Iupac {
+                    motif: pattern.into_bytes(),
+                    max_mismatches: mismatches,
+                }
+            };
+            search::find(
+                pattern,
+                seqs,
+                fasta.as_deref(),
+                circular,
+                use_0_based_coords,
+            )
+        }
+        Commands::PwmScan {
+            matrix,
+            seqs,
+            fasta,
+            p_value,
+            pseudocount,
+            show_thresholds,
+            use_0_based_coords,
+        } => pwm::read_pwms(&matrix, pseudocount).and_then(|pwms| {
+            if show_thresholds {
+                Ok(pwm::thresholds(&pwms, p_value))
+            } else {
+                pwm::pwm_scan(&pwms, seqs, fasta.as_deref(), p_value, use_0_based_coords)
+            }
+        }),
+        Commands::Guides {
+            seqs,
+            fasta,
+            nuclease,
+            pam,
+            length,
+            max_homopolymer,
+            circular,
+            use_0_based_coords,
+        } => {
+            let opts = guides::GuideOptions {
+                nuclease,
+                pam,
+                length,
+                max_homopolymer,
+                circular,
+            };
+            guides::guides(seqs, fasta.as_deref(), opts, use_0_based_coords)
+        }
+        Commands::OffTargets {
+            probe,
+            reference,
+            mismatches,
+            bulges,
+            hide_coords,
+            line_width,
+            use_0_based_coords,
+        } => {
+            let opts = offtargets::OffTargetOptions {
+                max_mismatches: mismatches,
+                max_bulges: bulges,
+            };
+            offtargets::off_targets(
+                &probe,
+                &reference,
+                opts,
+                line_width,
+                hide_coords,
+                use_0_based_coords,
+            )
+        }
+        Commands::Hamming {
+            seqs,
+            fasta,
+            ignore_case,
+            ignore_strand,
+        } => {
+            let opts = distance::DistanceOptions {
+                ignore_case,
+                ignore_strand,
+            };
+            distance::distances(distance::Metric::Hamming, seqs, fasta.as_deref(), opts)
+        }
+        Commands::EditDistance {
+            seqs,
+            fasta,
+            ignore_case,
+            ignore_strand,
+        } => {
+            let opts = distance::DistanceOptions {
+                ignore_case,
+                ignore_strand,
+            };
+            distance::distances(distance::Metric::Levenshtein, seqs, fasta.as_deref(), opts)
+        }
+        Commands::Barcodes {
+            seqs,
+            fasta,
+            metric,
+            min_distance,
+            min_gc,
+            max_gc,
+            max_homopolymer,
+            generate,
+            length,
+            seed,
+        } => {
+            let opts = barcodes::BarcodeOptions {
+                metric,
+                min_distance,
+                min_gc,
+                max_gc,
+                max_homopolymer,
+            };
+            barcodes::barcodes(seqs, fasta.as_deref(), opts, generate, length, seed)
+        }
+        Commands::Demux {
+            reads,
+            barcodes,
+            out_dir,
+            location,
+            mismatches,
+            keep_barcode,
+        } => {
+            let opts = demux::DemuxOptions {
+                location,
+                max_mismatches: mismatches,
+                keep_barcode,
+            };
+            demux::demux(&reads, &barcodes, &out_dir, opts)
+        }
+        Commands::Trim {
+            reads,
+            adapters,
+            error_rate,
+            min_overlap,
+            quality_cutoff,
+            min_length,
+        } => {
+            let opts = trim::TrimOptions {
+                adapters: adapters.into_iter().map(String::into_bytes).collect(),
+                error_rate,
+                min_overlap,
+                quality_cutoff,
+                min_length,
+            };
+            // Trimmed reads are written as they're processed, rather than returned for printing.
+            match trim::trim(&reads, opts, &mut std::io::stdout().lock()) {
+                Ok(()) => return Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+        Commands::SynthCheck {
+            seqs,
+            fasta,
+            min_gc,
+            max_gc,
+            window,
+            min_window_gc,
+            max_window_gc,
+            max_homopolymer,
+            max_repeat,
+            min_hairpin_stem,
+            max_hairpin_loop,
+            forbidden_enzymes,
+            use_0_based_coords,
+        } => {
+            let opts = synthesis::SynthesisOptions {
+                min_gc,
+                max_gc,
+                window,
+                min_window_gc,
+                max_window_gc,
+                max_homopolymer,
+                max_repeat,
+                min_hairpin_stem,
+                max_hairpin_loop,
+                forbidden_enzymes,
+            };
+            synthesis::synth_check(seqs, fasta.as_deref(), opts, use_0_based_coords)
+        }
         Commands::PairwiseLocal {
             seqs,
             gap_open,
@@ -428,12 +1672,14 @@ fn main() -> Result<()> {
             try_rc,
             line_width,
             use_0_based_coords,
+            circular,
         } => {
             let display_opts = DisplayOptions {
                 hide_coords,
                 try_rc,
                 line_width,
                 use_0_based_coords,
+                circular,
             };
             pairwise(
                 AlignmentCommand::Local,
@@ -451,12 +1697,14 @@ fn main() -> Result<()> {
             try_rc,
             line_width,
             use_0_based_coords,
+            circular,
         } => {
             let display_opts = DisplayOptions {
                 hide_coords,
                 try_rc,
                 line_width,
                 use_0_based_coords,
+                circular,
             };
 
             pairwise(
@@ -475,12 +1723,14 @@ fn main() -> Result<()> {
             try_rc,
             line_width,
             use_0_based_coords,
+            circular,
         } => {
             let display_opts = DisplayOptions {
                 hide_coords,
                 try_rc,
                 line_width,
                 use_0_based_coords,
+                circular,
             };
             pairwise(
                 AlignmentCommand::Global,
@@ -490,6 +1740,46 @@ fn main() -> Result<()> {
                 display_opts,
             )
         }
+        Commands::Slice {
+            seqs,
+            start,
+            end,
+            use_0_based_coords,
+            reverse_strand,
+            circular,
+        } => slice_sequence(
+            seqs,
+            start,
+            end,
+            use_0_based_coords,
+            reverse_strand,
+            circular,
+        ),
+        Commands::Rotate {
+            seqs,
+            position,
+            motif,
+            canonical,
+        } => circular::rotate_sequence(seqs, position, motif, canonical),
+        Commands::Digest {
+            seqs,
+            enzymes,
+            circular,
+            use_0_based_coords,
+            list_enzymes,
+        } => {
+            if list_enzymes {
+                Ok(digest::list_enzymes())
+            } else {
+                digest::digest(seqs, enzymes, circular, use_0_based_coords)
+            }
+        }
+        Commands::Gel {
+            lanes,
+            ladder,
+            percent,
+            svg,
+        } => gel::gel(lanes, ladder, percent, svg),
     };
 
     match output {
@@ -508,17 +1798,80 @@ mod tests {
     #[test]
     fn test_reverse_complement() {
         let seqs = vec!["GATTACA".to_string()];
-        let rc = build_reverse_complement(seqs).unwrap();
+        let rc = build_reverse_complement(seqs, false).unwrap();
         assert_eq!("TGTAATC".to_string(), rc);
     }
 
     #[test]
     fn test_reverse_complement_with_pairwise_cruft() {
         let seqs = vec!["GATT".to_string(), "ACA-TTGA".to_string()];
-        let rc = build_reverse_complement(seqs).unwrap();
+        let rc = build_reverse_complement(seqs, false).unwrap();
         assert_eq!("TCAA-TGT AATC".to_string(), rc);
     }
 
+    #[test]
+    fn test_reverse_complement_preserves_case() {
+        let seqs = vec!["GATtaca".to_string()];
+        let rc = build_reverse_complement(seqs, false).unwrap();
+        assert_eq!("tgtaATC".to_string(), rc);
+    }
+
+    #[test]
+    fn test_reverse_complement_rna() {
+        let seqs = vec!["GAUU".to_string(), "ACA".to_string()];
+        let rc = build_reverse_complement(seqs, false).unwrap();
+        assert_eq!("UGU AAUC".to_string(), rc);
+    }
+
+    #[test]
+    fn test_reverse_complement_forced_rna() {
+        let seqs = vec!["GGCA".to_string()];
+        let rc = build_reverse_complement(seqs, true).unwrap();
+        assert_eq!("UGCC".to_string(), rc);
+    }
+
+    #[test]
+    fn test_reverse_complement_mixed_dna_rna() {
+        let seqs = vec!["GAT".to_string(), "-UACA".to_string()];
+        let err = build_reverse_complement(seqs, false).unwrap_err();
+        assert_eq!(err.to_string(), "Mixed DNA/RNA base: 'U' at position 3");
+    }
+
+    #[test]
+    fn test_reverse_complement_forced_rna_with_thymine() {
+        let seqs = vec!["GATTACA".to_string()];
+        let err = build_reverse_complement(seqs, true).unwrap_err();
+        assert_eq!(err.to_string(), "DNA base in RNA sequence: 'T' at position 2");
+    }
+
+    #[test]
+    fn test_complement_with_pairwise_cruft() {
+        let seqs = vec!["GATT".to_string(), "ACA-TTGA".to_string()];
+        let complement = build_complement(seqs, false).unwrap();
+        assert_eq!("CTAA TGT-AACT".to_string(), complement);
+    }
+
+    #[test]
+    fn test_reverse_with_pairwise_cruft() {
+        let seqs = vec!["GATT".to_string(), "ACA-TTGA".to_string()];
+        let reversed = build_reverse(seqs).unwrap();
+        assert_eq!("AGTT-ACA TTAG".to_string(), reversed);
+    }
+
+    #[test]
+    fn test_transcribe() {
+        let seqs = vec!["GATT".to_string(), "ACA-ttga".to_string()];
+        let rna = transcribe(seqs).unwrap();
+        assert_eq!("GAUU ACA-uuga".to_string(), rna);
+    }
+
+    #[test]
+    fn test_back_transcribe() {
+        let seqs = vec!["GAUU".to_string(), "ACA-uuga".to_string()];
+        let dna = back_transcribe(seqs).unwrap();
+        assert_eq!("GATT ACA-ttga".to_string(), dna);
+    }
+
     #[test]
     fn test_length() {
         let seqs = vec!["GATTACA".to_string()];
@@ -540,6 +1893,33 @@ mod tests {
         assert_eq!(length, 8.to_string());
     }
 
+    #[test]
+    fn test_length_masked() {
+        let seqs = vec!["GATta".to_string(), "c-A".to_string()];
+        let length = get_masked_seq_length(seqs).unwrap();
+        assert_eq!(length, "total\t7\nmasked\t3\nunmasked\t4");
+    }
+
+    #[test]
+    fn test_gc_content_masked() {
+        let seqs = vec!["GGaa".to_string(), "ccTA".to_string()];
+        let gc = masked_gc_content(seqs).unwrap();
+        assert_eq!(
+            gc,
+            "total\t0.5000000000000000\nmasked\t0.5000000000000000\nunmasked\t0.5000000000000000"
+        );
+    }
+
+    #[test]
+    fn test_gc_content_masked_without_masked_bases() {
+        let seqs = vec!["GGAT".to_string()];
+        let gc = masked_gc_content(seqs).unwrap();
+        assert_eq!(
+            gc,
+            "total\t0.5000000000000000\nmasked\tn/a\nunmasked\t0.5000000000000000"
+        );
+    }
+
     #[test]
     fn test_gc_content() {
         let seqs = vec!["GGG".to_string(), "GAA-TA".to_string()];
@@ -547,6 +1927,29 @@ mod tests {
         assert_eq!(gc, "0.5000000000000000");
     }
 
+    #[test]
+    fn test_windowed_gc_content() {
+        let seqs = vec!["GGAA".to_string(), "TTC-C".to_string()];
+        let track = windowed_gc_content(seqs, 4, 2).unwrap();
+        assert_eq!(
+            track,
+            "0\t4\t0.5000\t1.0000\n2\t6\t0.0000\tn/a\n4\t8\t0.5000\t-1.0000"
+        );
+    }
+
+    #[test]
+    fn test_windowed_gc_content_incomplete_windows_skipped() {
+        let seqs = vec!["GGAATTC".to_string()];
+        let track = windowed_gc_content(seqs, 3, 3).unwrap();
+        assert_eq!(track, "0\t3\t0.6667\t1.0000\n3\t6\t0.0000\tn/a");
+    }
+
+    #[test]
+    fn test_windowed_gc_content_window_too_large() {
+        let seqs = vec!["GGAATTC".to_string()];
+        assert!(windowed_gc_content(seqs, 8, 1).is_err());
+    }
+
     #[test]
     fn test_compute_gc_content_0() {
         let seqs = vec!["AT".to_string(), "TTAA".to_string()];
@@ -589,6 +1992,7 @@ mod tests {
             line_width: 60,
             try_rc: false,
             use_0_based_coords: true,
+            circular: false,
         };
         let actual = pairwise(
             AlignmentCommand::Local,
@@ -609,6 +2013,7 @@ mod tests {
             line_width: 60,
             try_rc: false,
             use_0_based_coords: true,
+            circular: false,
         };
         let actual = pairwise(
             AlignmentCommand::Semiglobal,
@@ -629,6 +2034,7 @@ mod tests {
             line_width: 60,
             try_rc: false,
             use_0_based_coords: true,
+            circular: false,
         };
         let actual = pairwise(
             AlignmentCommand::Global,
@@ -642,6 +2048,27 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_pairwise_semiglobal_preserves_case() {
+        let opts = DisplayOptions {
+            hide_coords: true,
+            line_width: 60,
+            try_rc: false,
+            use_0_based_coords: true,
+            circular: false,
+        };
+        let actual = pairwise(
+            AlignmentCommand::Semiglobal,
+            vec!["ACagt".to_string(), "acGT".to_string()],
+            2,
+            1,
+            opts
+        )
+        .unwrap();
+        let expected = "ACagt\n|| ||\nac-GT";
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_pairwise_semiglobal_hide_coords() {
         let opts = DisplayOptions {
@@ -649,6 +2076,7 @@ mod tests {
             line_width: 60,
             try_rc: false,
             use_0_based_coords: true,
+            circular: false,
         };
         let actual = pairwise(
             AlignmentCommand::Semiglobal,
@@ -669,6 +2097,7 @@ mod tests {
             line_width: 60,
             try_rc: true,
             use_0_based_coords: true,
+            circular: false,
         };
         let actual = pairwise(
             AlignmentCommand::Semiglobal,
@@ -689,6 +2118,7 @@ mod tests {
             line_width: 60,
             try_rc: false,
             use_0_based_coords: true,
+            circular: false,
         };
         let actual = pairwise(
             AlignmentCommand::Semiglobal,
@@ -709,6 +2139,7 @@ mod tests {
             line_width: 60,
             try_rc: true,
             use_0_based_coords: true,
+            circular: false,
         };
         let actual = pairwise(
             AlignmentCommand::Semiglobal,
@@ -721,4 +2152,125 @@ mod tests {
         let expected = "0 AC----GT 4\n  ||    ||\n0 ACAAAAGT 8";
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_circular_reverse_complement() {
+        let seqs = vec!["AAG".to_string(), "GT".to_string()];
+        let rc = build_circular_reverse_complement(seqs, Some(3), false).unwrap();
+        assert_eq!(rc, "TTACC");
+    }
+
+    #[test]
+    fn test_circular_reverse_complement_invalid_origin() {
+        let seqs = vec!["AAGGT".to_string()];
+        assert!(build_circular_reverse_complement(seqs, Some(6), false).is_err());
+    }
+
+    #[test]
+    fn test_pairwise_semiglobal_circular() {
+        let opts = DisplayOptions {
+            hide_coords: false,
+            line_width: 60,
+            try_rc: false,
+            use_0_based_coords: false,
+            circular: true,
+        };
+        let actual = pairwise(
+            AlignmentCommand::Semiglobal,
+            vec![
+                "GATTACAACGT".to_string(),
+                "ACGTCCCCCCCCCCGATTACA".to_string(),
+            ],
+            2,
+            1,
+            opts,
+        )
+        .unwrap();
+        let expected = " 1 GATTACAACGT 11\n   |||||||||||\n15 GATTACAACGT 4";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_pairwise_global_circular() {
+        let opts = DisplayOptions {
+            hide_coords: false,
+            line_width: 60,
+            try_rc: false,
+            use_0_based_coords: false,
+            circular: true,
+        };
+        let actual = pairwise(
+            AlignmentCommand::Global,
+            vec!["TTACACGT".to_string(), "ACGTCCCGATTAC".to_string()],
+            2,
+            1,
+            opts,
+        )
+        .unwrap();
+        let expected = " 1 TTACACGT----- 8\n   ||||||||     \n10 TTACACGTCCCGA 9";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_slice_one_based() {
+        let seqs = vec!["GAT-TACA".to_string()];
+        let region = slice_sequence(seqs, 2, 4, false, false, false).unwrap();
+        assert_eq!(region, "ATT");
+    }
+
+    #[test]
+    fn test_slice_zero_based() {
+        let seqs = vec!["GAT".to_string(), "TACA".to_string()];
+        let region = slice_sequence(seqs, 2, 4, true, false, false).unwrap();
+        assert_eq!(region, "TT");
+    }
+
+    #[test]
+    fn test_slice_reverse_strand() {
+        let seqs = vec!["GGCGATTACAATGACA".to_string()];
+        let region = slice_sequence(seqs, 7, 13, false, true, false).unwrap();
+        assert_eq!(region, "CATTGTA");
+    }
+
+    #[test]
+    fn test_slice_reverse_strand_rna() {
+        let seqs = vec!["ACGU".to_string()];
+        let region = slice_sequence(seqs, 1, 4, false, true, false).unwrap();
+        assert_eq!(region, "ACGU");
+        let seqs = vec!["GGCGAUUACA".to_string()];
+        let region = slice_sequence(seqs, 8, 10, false, true, false).unwrap();
+        assert_eq!(region, "UGU");
+    }
+
+    #[test]
+    fn test_slice_circular() {
+        let seqs = vec!["GATTACA".to_string()];
+        let region = slice_sequence(seqs.clone(), 6, 2, false, false, true).unwrap();
+        assert_eq!(region, "CAGA");
+        assert!(slice_sequence(seqs, 6, 2, false, false, false).is_err());
+    }
+
+    #[test]
+    fn test_slice_circular_start_just_after_end() {
+        let seqs = vec!["GATTACA".to_string()];
+        let region = slice_sequence(seqs.clone(), 3, 2, false, false, true).unwrap();
+        assert_eq!(region, "TTACAGA");
+        assert!(slice_sequence(seqs, 3, 2, false, false, false).is_err());
+    }
+
+    #[test]
+    fn test_slice_circular_start_past_end_of_sequence() {
+        let seqs = vec!["ACGT".to_string()];
+        assert!(slice_sequence(seqs.clone(), 5, 4, false, false, true).is_err());
+        assert!(slice_sequence(seqs, 4, 3, true, false, true).is_err());
+    }
+
+    #[test]
+    fn test_slice_out_of_bounds() {
+        let seqs = vec!["GATTACA".to_string()];
+        assert!(slice_sequence(seqs.clone(), 0, 3, false, false, false).is_err());
+        assert!(slice_sequence(seqs, 1, 8, false, false, false).is_err());
+        let seqs = vec!["GATTÄCA".to_string()];
+        assert!(slice_sequence(seqs, 5, 7, false, false, false).is_err());
+    }
 }
@@ -0,0 +1,273 @@
+use crate::{compute_seq_length, confirm_valid_nucleic_acid};
+use anyhow::{bail, Result};
+use bio::alphabets::dna::revcomp;
+use clap::ValueEnum;
+
+const AVOGADRO: f64 = 6.02214076e23;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Molecule {
+    /// Single-stranded DNA
+    Ssdna,
+    /// Double-stranded DNA
+    Dsdna,
+    /// Single-stranded RNA
+    Rna,
+}
+
+pub enum Concentration {
+    NgPerUl(f64),
+    Nanomolar(f64),
+    CopiesPerUl(f64),
+    A260(f64),
+}
+
+fn base_index(base: u8) -> usize {
+    match base {
+        b'A' => 0,
+        b'C' => 1,
+        b'G' => 2,
+        _ => 3,
+    }
+}
+
+/// Masses of nucleotides within a strand (i.e. less a water molecule), in the order A, C, G, T/U.
+const DNA_NUCLEOTIDE_MASSES: [f64; 4] = [313.21, 289.18, 329.21, 304.2];
+const RNA_NUCLEOTIDE_MASSES: [f64; 4] = [329.21, 305.18, 345.21, 306.17];
+
+/// Removes the 5' phosphate, since synthetic oligonucleotides don't have one.
+const FIVE_PRIME_HYDROXYL: f64 = -61.96;
+
+/// Extinction coefficients at 260 nm of single nucleotides and of nearest-neighbor pairs
+/// (Cantor et al., 1970), in the order A, C, G, T/U.
+const DNA_EXTINCTION: [f64; 4] = [15400.0, 7400.0, 11500.0, 8700.0];
+const DNA_PAIR_EXTINCTION: [[f64; 4]; 4] = [
+    [27400.0, 21200.0, 25000.0, 22800.0],
+    [21200.0, 14600.0, 18000.0, 15200.0],
+    [25200.0, 17600.0, 21600.0, 20000.0],
+    [23400.0, 16200.0, 19000.0, 16800.0],
+];
+const RNA_EXTINCTION: [f64; 4] = [15400.0, 7200.0, 11500.0, 9900.0];
+const RNA_PAIR_EXTINCTION: [[f64; 4]; 4] = [
+    [27400.0, 21000.0, 25000.0, 24000.0],
+    [21000.0, 14200.0, 17800.0, 16200.0],
+    [25200.0, 17400.0, 21600.0, 21200.0],
+    [24600.0, 17200.0, 20000.0, 19600.0],
+];
+
+/// Average molecular weights per base (or base pair) and of the ends, for when only the length
+/// of a sequence is known.
+fn average_molecular_weight(molecule: Molecule, length: usize) -> f64 {
+    let (per_base, ends) = match molecule {
+        Molecule::Ssdna => (303.7, 79.0),
+        Molecule::Dsdna => (607.4, 157.9),
+        Molecule::Rna => (320.5, 159.0),
+    };
+    per_base * length as f64 + ends
+}
+
+fn strand_molecular_weight(seq: &[u8], masses: &[f64; 4]) -> f64 {
+    let total: f64 = seq.iter().map(|base| masses[base_index(*base)]).sum();
+    total + FIVE_PRIME_HYDROXYL
+}
+
+fn strand_extinction(seq: &[u8], singles: &[f64; 4], pairs: &[[f64; 4]; 4]) -> f64 {
+    if seq.len() == 1 {
+        return singles[base_index(seq[0])];
+    }
+    let pair_total: f64 = seq
+        .windows(2)
+        .map(|pair| pairs[base_index(pair[0])][base_index(pair[1])])
+        .sum();
+    let internal_total: f64 = seq[1..seq.len() - 1]
+        .iter()
+        .map(|base| singles[base_index(*base)])
+        .sum();
+    pair_total - internal_total
+}
+
+/// Molecular weight (in g/mol) of a sequence, assuming no 5' phosphate. Double-stranded DNA is
+/// the sum of both strands.
+pub fn molecular_weight(seq: &[u8], molecule: Molecule) -> f64 {
+    match molecule {
+        Molecule::Ssdna => strand_molecular_weight(seq, &DNA_NUCLEOTIDE_MASSES),
+        Molecule::Dsdna => {
+            strand_molecular_weight(seq, &DNA_NUCLEOTIDE_MASSES)
+                + strand_molecular_weight(&revcomp(seq), &DNA_NUCLEOTIDE_MASSES)
+        }
+        Molecule::Rna => strand_molecular_weight(seq, &RNA_NUCLEOTIDE_MASSES),
+    }
+}
+
+/// Molar extinction coefficient at 260 nm by the nearest-neighbor method. For double-stranded
+/// DNA, the sum of both strands is corrected for hypochromicity (Tataurov et al., 2008).
+pub fn extinction_coefficient(seq: &[u8], molecule: Molecule) -> f64 {
+    match molecule {
+        Molecule::Ssdna => strand_extinction(seq, &DNA_EXTINCTION, &DNA_PAIR_EXTINCTION),
+        Molecule::Dsdna => {
+            let strands = strand_extinction(seq, &DNA_EXTINCTION, &DNA_PAIR_EXTINCTION)
+                + strand_extinction(&revcomp(seq), &DNA_EXTINCTION, &DNA_PAIR_EXTINCTION);
+            let gc =
+                seq.iter().filter(|b| matches!(b, b'G' | b'C')).count() as f64 / seq.len() as f64;
+            let hypochromicity = 0.287 * (1.0 - gc) + 0.059 * gc;
+            (1.0 - hypochromicity) * strands
+        }
+        Molecule::Rna => strand_extinction(seq, &RNA_EXTINCTION, &RNA_PAIR_EXTINCTION),
+    }
+}
+
+fn confirm_matches_molecule(seq: &[u8], molecule: Molecule) -> Result<()> {
+    let (wrong, hint) = match molecule {
+        Molecule::Rna => (b'T', "use --molecule ssdna or dsdna"),
+        _ => (b'U', "use --molecule rna"),
+    };
+    if let Some(i) = seq.iter().position(|b| *b == wrong) {
+        bail!(
+            "Unexpected base: '{}' at position {i} ({hint})",
+            wrong as char
+        );
+    }
+    Ok(())
+}
+
+/// Converts a concentration to nanomolar.
+fn to_nanomolar(
+    concentration: &Concentration,
+    molecular_weight: f64,
+    extinction: Option<f64>,
+) -> Result<f64> {
+    Ok(match concentration {
+        Concentration::NgPerUl(ng) => ng * 1e6 / molecular_weight,
+        Concentration::Nanomolar(nm) => *nm,
+        Concentration::CopiesPerUl(copies) => copies / (AVOGADRO * 1e-15),
+        Concentration::A260(a260) => match extinction {
+            Some(extinction) => a260 / extinction * 1e9,
+            None => bail!("A sequence is needed to convert from A260"),
+        },
+    })
+}
+
+pub fn mw(
+    seqs: Vec<String>,
+    molecule: Molecule,
+    length: Option<usize>,
+    concentration: Option<Concentration>,
+) -> Result<String> {
+    let (length, molecular_weight, extinction) = match length {
+        Some(length) => (length, average_molecular_weight(molecule, length), None),
+        None => {
+            let seq = seqs.join("").replace(" ", "").replace("-", "");
+            confirm_valid_nucleic_acid(&seq)?;
+            if seq.is_empty() {
+                bail!("A sequence or --length is required");
+            }
+            let seq = seq.to_ascii_uppercase().into_bytes();
+            confirm_matches_molecule(&seq, molecule)?;
+            (
+                compute_seq_length(&seqs),
+                molecular_weight(&seq, molecule),
+                Some(extinction_coefficient(&seq, molecule)),
+            )
+        }
+    };
+    let mut output = vec![
+        format!("length\t{length}"),
+        format!("molecular_weight\t{:.2}", molecular_weight),
+        format!(
+            "extinction_coefficient\t{}",
+            match extinction {
+                Some(extinction) => format!("{:.0}", extinction),
+                None => "n/a".to_string(),
+            }
+        ),
+    ];
+    if let Some(concentration) = concentration {
+        let nm = to_nanomolar(&concentration, molecular_weight, extinction)?;
+        output.push(format!("ng_per_ul\t{:.4}", nm * molecular_weight / 1e6));
+        output.push(format!("nm\t{:.4}", nm));
+        output.push(format!("copies_per_ul\t{:.4e}", nm * AVOGADRO * 1e-15));
+        if let Some(extinction) = extinction {
+            output.push(format!("a260\t{:.4}", nm * 1e-9 * extinction));
+        }
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_molecular_weight() {
+        assert!((molecular_weight(b"ACGT", Molecule::Ssdna) - 1173.84).abs() < 1e-6);
+        assert!((molecular_weight(b"ACGU", Molecule::Rna) - 1223.81).abs() < 1e-6);
+        // ACGT is its own reverse complement.
+        assert!((molecular_weight(b"ACGT", Molecule::Dsdna) - 2347.68).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_extinction_coefficient() {
+        // ApC + CpG + GpT - C - G
+        assert_eq!(
+            extinction_coefficient(b"ACGT", Molecule::Ssdna),
+            21200.0 + 18000.0 + 20000.0 - 7400.0 - 11500.0
+        );
+        assert_eq!(extinction_coefficient(b"G", Molecule::Ssdna), 11500.0);
+        assert_eq!(extinction_coefficient(b"AU", Molecule::Rna), 24000.0);
+    }
+
+    #[test]
+    fn test_ds_extinction_includes_hypochromicity() {
+        let ss = extinction_coefficient(b"AATT", Molecule::Ssdna);
+        let ds = extinction_coefficient(b"AATT", Molecule::Dsdna);
+        assert!((ds - 2.0 * ss * (1.0 - 0.287)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_concentration_conversions() {
+        // 1 kb of dsDNA at 10 ng/uL.
+        let output = mw(
+            vec![],
+            Molecule::Dsdna,
+            Some(1000),
+            Some(Concentration::NgPerUl(10.0)),
+        )
+        .unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "molecular_weight\t607557.90");
+        assert_eq!(lines[2], "extinction_coefficient\tn/a");
+        assert_eq!(lines[4], "nm\t16.4593");
+        assert_eq!(lines[5], "copies_per_ul\t9.9120e9");
+        assert!(mw(
+            vec![],
+            Molecule::Dsdna,
+            Some(1000),
+            Some(Concentration::A260(1.0))
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let seqs = vec!["ACGT ACGT".to_string()];
+        let from_nm = mw(
+            seqs.clone(),
+            Molecule::Ssdna,
+            None,
+            Some(Concentration::Nanomolar(100.0)),
+        );
+        let from_copies = mw(
+            seqs,
+            Molecule::Ssdna,
+            None,
+            Some(Concentration::CopiesPerUl(100.0 * AVOGADRO * 1e-15)),
+        );
+        assert_eq!(from_nm.unwrap(), from_copies.unwrap());
+    }
+
+    #[test]
+    fn test_wrong_molecule() {
+        assert!(mw(vec!["ACGU".to_string()], Molecule::Dsdna, None, None).is_err());
+        assert!(mw(vec!["ACGT".to_string()], Molecule::Rna, None, None).is_err());
+    }
+}
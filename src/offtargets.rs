@@ -0,0 +1,328 @@
+use crate::digest::Strand;
+use crate::fasta;
+use crate::iupac;
+use crate::{format_display_lines, make_display_lines};
+use anyhow::{bail, Result};
+use bio::alignment::{Alignment, AlignmentMode, AlignmentOperation};
+use bio::alphabets::dna::revcomp;
+use std::path::Path;
+
+pub struct OffTargetOptions {
+    pub max_mismatches: usize,
+    pub max_bulges: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Site {
+    /// Zero-based start and (exclusive) end on the forward strand of the reference.
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+    pub mismatches: usize,
+    pub bulges: usize,
+    /// How the probe (reverse complemented for reverse strand sites) aligns to the reference.
+    /// Insertions are bases of the probe missing from the reference (RNA bulges), and deletions
+    /// are extra bases in the reference (DNA bulges).
+    pub operations: Vec<AlignmentOperation>,
+}
+
+struct Search<'a> {
+    probe: &'a [u8],
+    target: &'a [u8],
+    opts: &'a OffTargetOptions,
+    operations: Vec<AlignmentOperation>,
+    mismatches: usize,
+    bulges: usize,
+    /// The alignment with the fewest differences so far: mismatches, bulges, end and operations.
+    best: Option<(usize, usize, usize, Vec<AlignmentOperation>)>,
+}
+
+impl Search<'_> {
+    fn is_better(&self) -> bool {
+        match &self.best {
+            None => true,
+            Some((mismatches, bulges, _, _)) => {
+                (self.mismatches + self.bulges, self.bulges) < (mismatches + bulges, *bulges)
+            }
+        }
+    }
+
+    fn push(&mut self, operation: AlignmentOperation, probe_index: usize, target_index: usize) {
+        self.operations.push(operation);
+        self.extend(probe_index, target_index);
+        self.operations.pop();
+    }
+
+    /// Tries every way of aligning the rest of the probe within the limits. Bulges aren't
+    /// allowed at either end, where they'd be indistinguishable from a shorter site, or next to
+    /// a bulge of the other kind, which is just a mismatch.
+    fn extend(&mut self, probe_index: usize, target_index: usize) {
+        if probe_index == self.probe.len() {
+            if self.is_better() {
+                self.best = Some((
+                    self.mismatches,
+                    self.bulges,
+                    target_index,
+                    self.operations.clone(),
+                ));
+            }
+            return;
+        }
+        if target_index == self.target.len() {
+            return;
+        }
+        if iupac::matches(self.probe[probe_index], self.target[target_index]) {
+            self.push(AlignmentOperation::Match, probe_index + 1, target_index + 1);
+        } else if self.mismatches < self.opts.max_mismatches {
+            self.mismatches += 1;
+            self.push(AlignmentOperation::Subst, probe_index + 1, target_index + 1);
+            self.mismatches -= 1;
+        }
+        if self.bulges == self.opts.max_bulges || probe_index == 0 {
+            return;
+        }
+        let last = self.operations.last().copied();
+        self.bulges += 1;
+        if probe_index + 1 < self.probe.len() && last != Some(AlignmentOperation::Del) {
+            self.push(AlignmentOperation::Ins, probe_index + 1, target_index);
+        }
+        if last != Some(AlignmentOperation::Ins) {
+            self.push(AlignmentOperation::Del, probe_index, target_index + 1);
+        }
+        self.bulges -= 1;
+    }
+}
+
+/// Finds every site in the reference where the probe aligns on either strand with at most the
+/// given numbers of mismatches and bulges. Bulges let the probe align from several nearby
+/// starts, so overlapping sites on the same strand are collapsed to the one with the fewest
+/// differences, then the fewest bulges.
+pub fn find_sites(probe: &[u8], target: &[u8], opts: &OffTargetOptions) -> Vec<Site> {
+    let rc_probe = revcomp(probe);
+    let mut probes = vec![(Strand::Forward, probe.to_vec())];
+    // A palindromic probe would find every site twice.
+    if !rc_probe.eq_ignore_ascii_case(probe) {
+        probes.push((Strand::Reverse, rc_probe));
+    }
+    let mut sites = vec![];
+    for start in 0..target.len() {
+        for (strand, probe) in &probes {
+            let mut search = Search {
+                probe,
+                target: &target[start..],
+                opts,
+                operations: vec![],
+                mismatches: 0,
+                bulges: 0,
+                best: None,
+            };
+            search.extend(0, 0);
+            if let Some((mismatches, bulges, end, operations)) = search.best {
+                add_site(
+                    &mut sites,
+                    Site {
+                        start,
+                        end: start + end,
+                        strand: *strand,
+                        mismatches,
+                        bulges,
+                        operations,
+                    },
+                );
+            }
+        }
+    }
+    sites.sort_by_key(|site| site.start);
+    sites
+}
+
+/// Adds a site, or replaces the last site on the same strand with it if they overlap and it has
+/// fewer differences. Sites must be added in order of their start.
+fn add_site(sites: &mut Vec<Site>, site: Site) {
+    let differences = |site: &Site| (site.mismatches + site.bulges, site.bulges);
+    match sites
+        .iter_mut()
+        .rev()
+        .find(|other| other.strand == site.strand)
+    {
+        Some(last) if site.start < last.end => {
+            if differences(&site) < differences(last) {
+                *last = site;
+            }
+        }
+        _ => sites.push(site),
+    }
+}
+
+fn format_site(
+    site: Site,
+    probe: &[u8],
+    target: &[u8],
+    line_width: usize,
+    hide_coords: bool,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    let a_is_rc = site.strand == Strand::Reverse;
+    let a = if a_is_rc {
+        revcomp(probe)
+    } else {
+        probe.to_vec()
+    };
+    let alignment = Alignment {
+        score: 0,
+        xstart: 0,
+        ystart: 0,
+        xend: a.len(),
+        yend: site.end - site.start,
+        xlen: a.len(),
+        ylen: site.end - site.start,
+        operations: site.operations,
+        mode: AlignmentMode::Custom,
+    };
+    let (mut display_lines, a_end) = make_display_lines(
+        alignment,
+        String::from_utf8(a)?,
+        String::from_utf8(target[site.start..site.end].to_vec())?,
+        line_width,
+    );
+    for line in &mut display_lines {
+        line.b_start += site.start;
+        line.b_end += site.start;
+    }
+    Ok(format!(
+        "{}\t{}\t{}\tmismatches={}\tbulges={}\n{}",
+        site.start + if use_0_based_coords { 0 } else { 1 },
+        site.end,
+        site.strand.symbol(),
+        site.mismatches,
+        site.bulges,
+        format_display_lines(
+            &display_lines,
+            hide_coords,
+            a_end,
+            a_is_rc,
+            use_0_based_coords
+        )
+    ))
+}
+
+pub fn off_targets(
+    probe: &str,
+    reference: &Path,
+    opts: OffTargetOptions,
+    line_width: usize,
+    hide_coords: bool,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    let probe = probe.replace(" ", "").replace("-", "");
+    if probe.is_empty() {
+        bail!("Probe is empty");
+    }
+    iupac::confirm_valid_iupac(&probe)?;
+    if opts.max_mismatches + opts.max_bulges >= probe.len() {
+        bail!("Mismatches and bulges must allow at least one base of the probe to match");
+    }
+    let probe = probe.into_bytes();
+    let mut output = vec![];
+    for record in fasta::read_records(reference)? {
+        iupac::confirm_valid_iupac(&record.seq)?;
+        let target = record.seq.as_bytes();
+        for site in find_sites(&probe, target, &opts) {
+            let formatted = format_site(
+                site,
+                &probe,
+                target,
+                line_width,
+                hide_coords,
+                use_0_based_coords,
+            )?;
+            output.push(format!(">{}\t{}", record.id, formatted));
+        }
+    }
+    Ok(output.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AlignmentOperation::*;
+
+    #[test]
+    fn test_mismatches_on_both_strands() {
+        let target = b"TTACGGATCTTTTGATCAGTTT";
+        let mut opts = OffTargetOptions {
+            max_mismatches: 1,
+            max_bulges: 0,
+        };
+        let sites = find_sites(b"ACGGATC", target, &opts);
+        assert_eq!(sites.len(), 2);
+        assert_eq!((sites[0].start, sites[0].end), (2, 9));
+        assert_eq!(sites[0].mismatches, 0);
+        // GATCAGT is one mismatch from GATCCGT, the reverse complement of the probe.
+        assert_eq!(sites[1].strand, Strand::Reverse);
+        assert_eq!((sites[1].start, sites[1].end), (13, 20));
+        assert_eq!(sites[1].mismatches, 1);
+        opts.max_mismatches = 0;
+        assert_eq!(find_sites(b"ACGGATC", target, &opts).len(), 1);
+    }
+
+    #[test]
+    fn test_bulges() {
+        // The reference has an extra T (a DNA bulge) and is missing a C (an RNA bulge).
+        let mut opts = OffTargetOptions {
+            max_mismatches: 0,
+            max_bulges: 1,
+        };
+        let sites = find_sites(b"ACGTACGA", b"GGACGTTACGAGG", &opts);
+        assert_eq!(sites.len(), 1);
+        assert_eq!((sites[0].start, sites[0].end), (2, 11));
+        assert_eq!(sites[0].bulges, 1);
+        assert_eq!(
+            sites[0].operations,
+            vec![Match, Match, Match, Match, Del, Match, Match, Match, Match]
+        );
+        let sites = find_sites(b"ACGTACGA", b"GGACGTAGAGG", &opts);
+        assert_eq!(sites.len(), 1);
+        assert!(sites[0].operations.contains(&Ins));
+        opts.max_bulges = 0;
+        assert!(find_sites(b"ACGTACGA", b"GGACGTAGAGG", &opts).is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_sites_reported_once() {
+        let opts = OffTargetOptions {
+            max_mismatches: 1,
+            max_bulges: 1,
+        };
+        // With a mismatch and a bulge, the probe also aligns starting a base either side of the
+        // exact match, and its reverse complement at several overlapping places.
+        let sites = find_sites(b"ACGTACGA", b"TTTTACGTACGATTTT", &opts);
+        let found: Vec<(usize, usize, Strand, usize, usize)> = sites
+            .iter()
+            .map(|s| (s.start, s.end, s.strand, s.mismatches, s.bulges))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, 8, Strand::Reverse, 1, 1),
+                (4, 12, Strand::Forward, 0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefers_fewest_differences() {
+        // The probe matches exactly, though a bulge would also fit.
+        let opts = OffTargetOptions {
+            max_mismatches: 1,
+            max_bulges: 1,
+        };
+        let sites = find_sites(b"AACCGGA", b"AACCGGAA", &opts);
+        let forward: Vec<&Site> = sites
+            .iter()
+            .filter(|s| s.strand == Strand::Forward)
+            .collect();
+        assert_eq!(forward[0].start, 0);
+        assert_eq!(forward[0].mismatches + forward[0].bulges, 0);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::fasta;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Amino acids in the order used to index the tables below.
+const AMINO_ACIDS: &[u8; 20] = b"ACDEFGHIKLMNPQRSTVWY";
+
+/// Average masses of amino acid residues (i.e. less a water molecule), as used by ExPASy.
+const RESIDUE_MASSES: [f64; 20] = [
+    71.0788, 103.1388, 115.0886, 129.1155, 147.1766, 57.0519, 137.1411, 113.1594, 128.1741,
+    113.1594, 131.1926, 114.1038, 97.1167, 128.1307, 156.1875, 87.0782, 101.1051, 99.1326,
+    186.2132, 163.1760,
+];
+
+const WATER_MASS: f64 = 18.01524;
+
+/// Kyte & Doolittle (1982) hydropathy values.
+const HYDROPATHY: [f64; 20] = [
+    1.8, 2.5, -3.5, -3.5, 2.8, -0.4, -3.2, 4.5, -3.9, 3.8, 1.9, -3.5, -1.6, -3.5, -4.5, -0.8, -0.7,
+    4.2, -0.9, -1.3,
+];
+
+/// Dipeptide instability weight values (Guruprasad et al., 1990), indexed by the first and
+/// second residue of each dipeptide.
+#[rustfmt::skip]
+const DIWV: [[f64; 20]; 20] = [
+    [1.0, 44.94, -7.49, 1.0, 1.0, 1.0, -7.49, 1.0, 1.0, 1.0, 1.0, 1.0, 20.26, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0], // A
+    [1.0, 1.0, 20.26, 1.0, 1.0, 1.0, 33.6, 1.0, 1.0, 20.26, 33.6, 1.0, 20.26, -6.54, 1.0, 1.0, 33.6, -6.54, 24.68, 1.0], // C
+    [1.0, 1.0, 1.0, 1.0, -6.54, 1.0, 1.0, 1.0, -7.49, 1.0, 1.0, 1.0, 1.0, 1.0, -6.54, 20.26, -14.03, 1.0, 1.0, 1.0], // D
+    [1.0, 44.94, 20.26, 33.6, 1.0, 1.0, -6.54, 20.26, 1.0, 1.0, 1.0, 1.0, 20.26, 20.26, 1.0, 20.26, 1.0, 1.0, -14.03, 1.0], // E
+    [1.0, 1.0, 13.34, 1.0, 1.0, 1.0, 1.0, 1.0, -14.03, 1.0, 1.0, 1.0, 20.26, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 33.601], // F
+    [-7.49, 1.0, 1.0, -6.54, 1.0, 13.34, 1.0, -7.49, -7.49, 1.0, 1.0, -7.49, 1.0, 1.0, 1.0, 1.0, -7.49, 1.0, 13.34, -7.49], // G
+    [1.0, 1.0, 1.0, 1.0, -9.37, -9.37, 1.0, 44.94, 24.68, 1.0, 1.0, 24.68, -1.88, 1.0, 1.0, 1.0, -6.54, 1.0, -1.88, 44.94], // H
+    [1.0, 1.0, 1.0, 44.94, 1.0, 1.0, 13.34, 1.0, -7.49, 20.26, 1.0, 1.0, -1.88, 1.0, 1.0, 1.0, 1.0, -7.49, 1.0, 1.0], // I
+    [1.0, 1.0, 1.0, 1.0, 1.0, -7.49, 1.0, -7.49, 1.0, -7.49, 33.6, 1.0, -6.54, 24.64, 33.6, 1.0, 1.0, -7.49, 1.0, 1.0], // K
+    [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -7.49, 1.0, 1.0, 1.0, 20.26, 33.6, 20.26, 1.0, 1.0, 1.0, 24.68, 1.0], // L
+    [13.34, 1.0, 1.0, 1.0, 1.0, 1.0, 58.28, 1.0, 1.0, 1.0, -1.88, 1.0, 44.94, -6.54, -6.54, 44.94, -1.88, 1.0, 1.0, 24.68], // M
+    [1.0, -1.88, 1.0, 1.0, -14.03, -14.03, 1.0, 44.94, 24.68, 1.0, 1.0, 1.0, -1.88, -6.54, 1.0, 1.0, -7.49, 1.0, -9.37, 1.0], // N
+    [20.26, -6.54, -6.54, 18.38, 20.26, 1.0, 1.0, 1.0, 1.0, 1.0, -6.54, 1.0, 20.26, 20.26, -6.54, 20.26, 1.0, 20.26, -1.88, 1.0], // P
+    [1.0, -6.54, 20.26, 20.26, -6.54, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 20.26, 20.26, 1.0, 44.94, 1.0, -6.54, 1.0, -6.54], // Q
+    [1.0, 1.0, 1.0, 1.0, 1.0, -7.49, 20.26, 1.0, 1.0, 1.0, 1.0, 13.34, 20.26, 20.26, 58.28, 44.94, 1.0, 1.0, 58.28, -6.54], // R
+    [1.0, 33.6, 1.0, 20.26, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 44.94, 20.26, 20.26, 20.26, 1.0, 1.0, 1.0, 1.0], // S
+    [1.0, 1.0, 1.0, 20.26, 13.34, -7.49, 1.0, 1.0, 1.0, 1.0, 1.0, -14.03, 1.0, -6.54, 1.0, 1.0, 1.0, 1.0, -14.03, 1.0], // T
+    [1.0, 1.0, -14.03, 1.0, 1.0, -7.49, 1.0, 1.0, -1.88, 1.0, 1.0, 1.0, 20.26, 1.0, 1.0, 1.0, -7.49, 1.0, 1.0, -6.54], // V
+    [-14.03, 1.0, 1.0, 1.0, 1.0, -9.37, 24.68, 1.0, 1.0, 13.34, 24.68, 13.34, 1.0, 1.0, 1.0, 1.0, -14.03, -7.49, 1.0, 1.0], // W
+    [24.68, 1.0, 24.68, -6.54, 1.0, -7.49, 13.34, 1.0, 1.0, 1.0, 44.94, 1.0, 13.34, 1.0, -15.91, 1.0, -7.49, 1.0, -9.37, 13.34], // Y
+];
+
+/// Molar extinction coefficients at 280 nm (Pace et al., 1995).
+const TRP_EXTINCTION: usize = 5500;
+const TYR_EXTINCTION: usize = 1490;
+const CYSTINE_EXTINCTION: usize = 125;
+
+/// Proteins with an instability index above this are predicted to be unstable.
+const INSTABILITY_THRESHOLD: f64 = 40.0;
+
+/// Side chain pK values (Bjellqvist et al., 1993), as used by ExPASy and Biopython.
+const POSITIVE_PKS: [(u8, f64); 3] = [(b'K', 10.0), (b'R', 12.0), (b'H', 5.98)];
+const NEGATIVE_PKS: [(u8, f64); 4] = [(b'D', 4.05), (b'E', 4.45), (b'C', 9.0), (b'Y', 10.0)];
+
+fn n_terminal_pk(residue: u8) -> f64 {
+    match residue {
+        b'A' => 7.59,
+        b'M' => 7.0,
+        b'S' => 6.93,
+        b'P' => 8.36,
+        b'T' => 6.82,
+        b'V' => 7.44,
+        b'E' => 7.7,
+        _ => 7.5,
+    }
+}
+
+fn c_terminal_pk(residue: u8) -> f64 {
+    match residue {
+        b'D' => 4.55,
+        b'E' => 4.75,
+        _ => 3.55,
+    }
+}
+
+fn index(residue: u8) -> usize {
+    AMINO_ACIDS
+        .iter()
+        .position(|aa| *aa == residue)
+        .expect("validated amino acid")
+}
+
+pub struct Protein {
+    residues: Vec<u8>,
+    counts: [usize; 20],
+}
+
+impl Protein {
+    /// Parses a protein sequence of the 20 standard amino acids, ignoring case and a trailing
+    /// stop codon.
+    pub fn new(seq: &str) -> Result<Protein> {
+        let seq = seq.to_ascii_uppercase();
+        let residues = seq.strip_suffix('*').unwrap_or(&seq).as_bytes().to_vec();
+        if residues.is_empty() {
+            bail!("Protein sequence is empty");
+        }
+        let mut counts = [0; 20];
+        for (i, residue) in residues.iter().enumerate() {
+            match AMINO_ACIDS.iter().position(|aa| aa == residue) {
+                Some(index) => counts[index] += 1,
+                None => bail!("Invalid amino acid: '{}' at position {i}", *residue as char),
+            }
+        }
+        Ok(Protein { residues, counts })
+    }
+
+    fn count(&self, residue: u8) -> usize {
+        self.counts[index(residue)]
+    }
+
+    fn mole_percent(&self, residue: u8) -> f64 {
+        100.0 * self.count(residue) as f64 / self.residues.len() as f64
+    }
+
+    /// Average molecular weight in daltons.
+    pub fn molecular_weight(&self) -> f64 {
+        let residues: f64 = self
+            .counts
+            .iter()
+            .zip(RESIDUE_MASSES)
+            .map(|(count, mass)| *count as f64 * mass)
+            .sum();
+        residues + WATER_MASS
+    }
+
+    /// Net charge at the given pH.
+    fn charge(&self, ph: f64) -> f64 {
+        let positive = |pk: f64| 1.0 / (1.0 + 10f64.powf(ph - pk));
+        let negative = |pk: f64| 1.0 / (1.0 + 10f64.powf(pk - ph));
+        let first = self.residues[0];
+        let last = self.residues[self.residues.len() - 1];
+        let mut charge = positive(n_terminal_pk(first)) - negative(c_terminal_pk(last));
+        for (residue, pk) in POSITIVE_PKS {
+            charge += self.count(residue) as f64 * positive(pk);
+        }
+        for (residue, pk) in NEGATIVE_PKS {
+            charge -= self.count(residue) as f64 * negative(pk);
+        }
+        charge
+    }
+
+    /// The pH at which the net charge is zero, found by bisection.
+    pub fn isoelectric_point(&self) -> f64 {
+        let (mut low, mut high) = (0.0, 14.0);
+        while high - low > 1e-4 {
+            let mid = (low + high) / 2.0;
+            if self.charge(mid) > 0.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        (low + high) / 2.0
+    }
+
+    /// Molar extinction coefficient at 280 nm in water, assuming either that every pair of
+    /// cysteines forms a cystine, or that all cysteines are reduced.
+    pub fn extinction_coefficients(&self) -> (usize, usize) {
+        let reduced = self.count(b'W') * TRP_EXTINCTION + self.count(b'Y') * TYR_EXTINCTION;
+        let cystines = reduced + self.count(b'C') / 2 * CYSTINE_EXTINCTION;
+        (cystines, reduced)
+    }
+
+    /// The instability index (Guruprasad et al., 1990).
+    pub fn instability_index(&self) -> f64 {
+        let total: f64 = self
+            .residues
+            .windows(2)
+            .map(|pair| DIWV[index(pair[0])][index(pair[1])])
+            .sum();
+        10.0 * total / self.residues.len() as f64
+    }
+
+    /// The relative volume occupied by aliphatic side chains (Ikai, 1980).
+    pub fn aliphatic_index(&self) -> f64 {
+        self.mole_percent(b'A')
+            + 2.9 * self.mole_percent(b'V')
+            + 3.9 * (self.mole_percent(b'I') + self.mole_percent(b'L'))
+    }
+
+    /// The grand average of hydropathy.
+    pub fn gravy(&self) -> f64 {
+        let total: f64 = self
+            .counts
+            .iter()
+            .zip(HYDROPATHY)
+            .map(|(count, hydropathy)| *count as f64 * hydropathy)
+            .sum();
+        total / self.residues.len() as f64
+    }
+}
+
+fn format_protparam(protein: &Protein) -> String {
+    let molecular_weight = protein.molecular_weight();
+    let (cystines, reduced) = protein.extinction_coefficients();
+    let instability_index = protein.instability_index();
+    let stability = if instability_index > INSTABILITY_THRESHOLD {
+        "unstable"
+    } else {
+        "stable"
+    };
+    [
+        format!("length\t{}", protein.residues.len()),
+        format!("molecular_weight\t{:.2}", molecular_weight),
+        format!("isoelectric_point\t{:.2}", protein.isoelectric_point()),
+        format!("extinction_coefficient\t{}", cystines),
+        format!("extinction_coefficient_reduced\t{}", reduced),
+        format!("abs_0.1%\t{:.4}", cystines as f64 / molecular_weight),
+        format!("abs_0.1%_reduced\t{:.4}", reduced as f64 / molecular_weight),
+        format!("instability_index\t{:.2}", instability_index),
+        format!("stability\t{}", stability),
+        format!("aliphatic_index\t{:.2}", protein.aliphatic_index()),
+        format!("gravy\t{:.4}", protein.gravy()),
+    ]
+    .join("\n")
+}
+
+pub fn protparam(seqs: Vec<String>, fasta: Option<&Path>) -> Result<String> {
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let mut output = vec![];
+    for record in records {
+        let protein = Protein::new(&record.seq)?;
+        if record.id.is_empty() {
+            output.push(format_protparam(&protein));
+        } else {
+            output.push(format!(">{}\n{}", record.id, format_protparam(&protein)));
+        }
+    }
+    Ok(output.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UBIQUITIN: &str =
+        "MQIFVKTLTGKTITLEVEPSDTIENVKAKIQDKEGIPPDQQRLIFAGKQLEDGRTLSDYNIQKESTLHLVLRLRGG";
+
+    #[test]
+    fn test_protparam_values() {
+        // Values reported by ExPASy ProtParam for human ubiquitin.
+        let protein = Protein::new(UBIQUITIN).unwrap();
+        assert_eq!(format!("{:.2}", protein.molecular_weight()), "8564.84");
+        assert_eq!(format!("{:.2}", protein.isoelectric_point()), "6.56");
+        assert_eq!(protein.extinction_coefficients(), (1490, 1490));
+        assert_eq!(format!("{:.3}", protein.gravy()), "-0.489");
+    }
+
+    #[test]
+    fn test_molecular_weight() {
+        assert!((Protein::new("G").unwrap().molecular_weight() - 75.06714).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_isoelectric_point_of_glycine() {
+        // Halfway between the N- and C-terminal pKs.
+        let pi = Protein::new("G").unwrap().isoelectric_point();
+        assert!((pi - (7.5 + 3.55) / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_instability_index() {
+        assert!((Protein::new("AC").unwrap().instability_index() - 224.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_extinction_coefficients() {
+        let protein = Protein::new("WYCC").unwrap();
+        assert_eq!(protein.extinction_coefficients(), (7115, 6990));
+    }
+
+    #[test]
+    fn test_aliphatic_index() {
+        let protein = Protein::new("AVIL").unwrap();
+        assert!((protein.aliphatic_index() - 25.0 * (1.0 + 2.9 + 3.9 * 2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_residues() {
+        assert!(Protein::new("MKX").is_err());
+        assert!(Protein::new("*").is_err());
+        assert!(Protein::new("mk*").is_ok());
+    }
+
+    #[test]
+    fn test_protparam_ignores_spaces_and_dashes() {
+        let actual = protparam(vec!["MK-W".to_string(), "Y".to_string()], None).unwrap();
+        assert!(actual.starts_with("length\t4\n"));
+    }
+}
@@ -0,0 +1,533 @@
+use crate::digest::Strand;
+use crate::fasta;
+use crate::iupac;
+use anyhow::{anyhow, bail, Context, Result};
+use bio::alphabets::dna::revcomp;
+use std::path::Path;
+
+/// Scores are rounded to this fraction of a bit when computing p-values, so that the score
+/// distribution can be found exactly by dynamic programming.
+const SCORE_RESOLUTION: f64 = 100.0;
+
+/// A uniform background distribution of A, C, G and T.
+const BACKGROUND: f64 = 0.25;
+
+/// MEME matrices are probabilities, which are turned back into counts using the number of
+/// sites, or this if the number isn't given.
+const DEFAULT_MEME_SITES: f64 = 20.0;
+
+fn base_index(base: u8) -> Option<usize> {
+    match base.to_ascii_uppercase() {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' | b'U' => Some(3),
+        _ => None,
+    }
+}
+
+/// A position frequency matrix, with the counts of A, C, G and T at each position.
+#[derive(Debug, PartialEq)]
+pub struct Motif {
+    pub name: String,
+    pub counts: Vec<[f64; 4]>,
+}
+
+fn parse_numbers(text: &str) -> Result<Vec<f64>> {
+    text.split_whitespace()
+        .map(|n| {
+            n.parse::<f64>()
+                .map_err(|_| anyhow!("Invalid number in motif matrix: '{n}'"))
+        })
+        .collect()
+}
+
+/// JASPAR matrices have a row for each base, optionally labelled and bracketed, e.g.
+/// "A [ 4 19 0 0 ]", under a "> ID name" header. Headerless matrices are also accepted.
+fn parse_jaspar(text: &str) -> Result<Vec<Motif>> {
+    let mut motifs = vec![];
+    let mut name = None;
+    let mut rows: Vec<(Option<usize>, Vec<f64>)> = vec![];
+    let mut finish = |name: Option<String>, rows: &mut Vec<(Option<usize>, Vec<f64>)>| {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        if rows.len() != 4 || rows.iter().any(|(_, row)| row.len() != rows[0].1.len()) {
+            bail!("JASPAR matrices must have four rows of equal length");
+        }
+        let mut counts = vec![[0.0; 4]; rows[0].1.len()];
+        for (i, (label, row)) in rows.iter().enumerate() {
+            let base = label.unwrap_or(i);
+            for (position, count) in row.iter().enumerate() {
+                counts[position][base] = *count;
+            }
+        }
+        let name = name.unwrap_or_else(|| format!("motif_{}", motifs.len() + 1));
+        motifs.push(Motif { name, counts });
+        rows.clear();
+        Ok(())
+    };
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(header) = line.strip_prefix('>') {
+            finish(name.take(), &mut rows)?;
+            name = Some(header.split_whitespace().collect::<Vec<_>>().join(" "));
+            continue;
+        }
+        let label = base_index(line.as_bytes()[0]);
+        let values = if label.is_some() { &line[1..] } else { line };
+        let values = values.replace(['[', ']'], " ");
+        rows.push((label, parse_numbers(&values)?));
+    }
+    finish(name, &mut rows)?;
+    Ok(motifs)
+}
+
+/// Finds a "key= value" field in a MEME matrix header line.
+fn meme_field(line: &str, key: &str) -> Option<f64> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let position = tokens.iter().position(|t| *t == key)?;
+    tokens.get(position + 1)?.parse().ok()
+}
+
+/// MEME motifs start with "MOTIF ID [name]", followed by a "letter-probability matrix" line and
+/// a row of A, C, G and T probabilities for each position.
+fn parse_meme(text: &str) -> Result<Vec<Motif>> {
+    let mut motifs = vec![];
+    let mut name = None;
+    let mut lines = text.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("MOTIF") {
+            name = Some(rest.split_whitespace().collect::<Vec<_>>().join(" "));
+        } else if line.starts_with("letter-probability matrix") {
+            let width = meme_field(line, "w=")
+                .ok_or_else(|| anyhow!("MEME matrix is missing its width (w=)"))?
+                as usize;
+            let sites = meme_field(line, "nsites=").unwrap_or(DEFAULT_MEME_SITES);
+            let mut counts = vec![];
+            for _ in 0..width {
+                let row = parse_numbers(lines.next().unwrap_or_default())?;
+                if row.len() != 4 {
+                    bail!("MEME matrix rows must have four probabilities");
+                }
+                counts.push([
+                    row[0] * sites,
+                    row[1] * sites,
+                    row[2] * sites,
+                    row[3] * sites,
+                ]);
+            }
+            let name = name
+                .take()
+                .unwrap_or_else(|| format!("motif_{}", motifs.len() + 1));
+            motifs.push(Motif { name, counts });
+        }
+    }
+    Ok(motifs)
+}
+
+/// TRANSFAC matrices have a "P0 A C G T" header followed by a numbered row for each position,
+/// and each motif ends with "//". The name is taken from the ID (or NA) line.
+fn parse_transfac(text: &str) -> Result<Vec<Motif>> {
+    let mut motifs = vec![];
+    let mut name = None;
+    let mut columns: Option<Vec<usize>> = None;
+    let mut counts = vec![];
+    for line in text.lines().map(str::trim) {
+        let (tag, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match tag {
+            "ID" | "NA" if name.is_none() => name = Some(rest.trim().to_string()),
+            "P0" | "PO" => {
+                let bases = rest
+                    .split_whitespace()
+                    .map(|b| base_index(b.as_bytes()[0]).filter(|_| b.len() == 1))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| anyhow!("Invalid TRANSFAC matrix header: {line}"))?;
+                columns = Some(bases);
+            }
+            "XX" | "//" if columns.is_some() => {
+                if !counts.is_empty() {
+                    let name = name
+                        .take()
+                        .unwrap_or_else(|| format!("motif_{}", motifs.len() + 1));
+                    motifs.push(Motif {
+                        name,
+                        counts: std::mem::take(&mut counts),
+                    });
+                }
+                columns = None;
+            }
+            "//" => name = None,
+            _ if tag.chars().all(|c| c.is_ascii_digit()) && !tag.is_empty() => {
+                let Some(columns) = &columns else {
+                    continue;
+                };
+                // Rows may end with a consensus base, which isn't a number.
+                let values: Vec<&str> = rest.split_whitespace().take(columns.len()).collect();
+                let values = parse_numbers(&values.join(" "))?;
+                if values.len() != columns.len() {
+                    bail!("TRANSFAC matrix row has too few values: {line}");
+                }
+                let mut row = [0.0; 4];
+                for (base, value) in columns.iter().zip(values) {
+                    row[*base] = value;
+                }
+                counts.push(row);
+            }
+            _ => {}
+        }
+    }
+    Ok(motifs)
+}
+
+pub fn parse_motifs(text: &str) -> Result<Vec<Motif>> {
+    let is_meme = text
+        .lines()
+        .any(|line| line.starts_with("MEME version") || line.contains("letter-probability matrix"));
+    let is_transfac = text.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("P0") || line.starts_with("PO ")
+    });
+    let motifs = if is_meme {
+        parse_meme(text)?
+    } else if is_transfac {
+        parse_transfac(text)?
+    } else {
+        parse_jaspar(text)?
+    };
+    if motifs.is_empty() {
+        bail!("No motifs found");
+    }
+    if let Some(motif) = motifs.iter().find(|m| m.counts.is_empty()) {
+        bail!("Motif {} has no positions", motif.name);
+    }
+    Ok(motifs)
+}
+
+/// A log-odds position weight matrix, in bits, against a uniform background.
+pub struct Pwm {
+    pub name: String,
+    scores: Vec<[f64; 4]>,
+    /// The probability of every score (in units of 1 / SCORE_RESOLUTION bits), from the lowest
+    /// possible score upwards, for sequences drawn from the background.
+    tail_probabilities: Vec<f64>,
+    min_score: i64,
+}
+
+impl Pwm {
+    /// Converts counts to log-odds scores, adding a pseudocount (split according to the
+    /// background) to each position so that unseen bases don't score negative infinity.
+    pub fn new(motif: &Motif, pseudocount: f64) -> Pwm {
+        let scores: Vec<[f64; 4]> = motif
+            .counts
+            .iter()
+            .map(|counts| {
+                let total: f64 = counts.iter().sum::<f64>() + pseudocount;
+                counts.map(|count| {
+                    let probability = (count + pseudocount * BACKGROUND) / total;
+                    (probability / BACKGROUND).log2()
+                })
+            })
+            .collect();
+
+        // The exact distribution of scores of background sequences, built up one position at a
+        // time.
+        let rounded: Vec<[i64; 4]> = scores
+            .iter()
+            .map(|row| row.map(|score| (score * SCORE_RESOLUTION).round() as i64))
+            .collect();
+        let min_score: i64 = rounded.iter().map(|row| *row.iter().min().unwrap()).sum();
+        let max_score: i64 = rounded.iter().map(|row| *row.iter().max().unwrap()).sum();
+        let mut distribution = vec![0.0; (max_score - min_score + 1) as usize];
+        distribution[0] = 1.0;
+        let mut row_min_total = 0;
+        for row in &rounded {
+            let row_min = *row.iter().min().unwrap();
+            let mut next = vec![0.0; distribution.len()];
+            for (offset, probability) in distribution.iter().enumerate() {
+                if *probability == 0.0 {
+                    continue;
+                }
+                for score in row {
+                    next[offset + (score - row_min) as usize] += probability * BACKGROUND;
+                }
+            }
+            distribution = next;
+            row_min_total += row_min;
+        }
+        debug_assert_eq!(row_min_total, min_score);
+        // Convert to the probability of a score at least this high.
+        let mut tail_probabilities = distribution;
+        for i in (0..tail_probabilities.len() - 1).rev() {
+            tail_probabilities[i] += tail_probabilities[i + 1];
+        }
+
+        Pwm {
+            name: motif.name.clone(),
+            scores,
+            tail_probabilities,
+            min_score,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn max_score(&self) -> f64 {
+        self.scores
+            .iter()
+            .map(|row| row.iter().copied().fold(f64::MIN, f64::max))
+            .sum()
+    }
+
+    /// The log-odds score of a window, or `None` if it contains ambiguous bases.
+    pub fn score(&self, window: &[u8]) -> Option<f64> {
+        let mut total = 0.0;
+        for (row, base) in self.scores.iter().zip(window) {
+            total += row[base_index(*base)?];
+        }
+        Some(total)
+    }
+
+    /// The probability that a background sequence scores at least this well.
+    pub fn p_value(&self, score: f64) -> f64 {
+        let rounded = (score * SCORE_RESOLUTION).round() as i64 - self.min_score;
+        if rounded < 0 {
+            1.0
+        } else {
+            self.tail_probabilities
+                .get(rounded as usize)
+                .copied()
+                .unwrap_or(0.0)
+        }
+    }
+
+    /// The lowest achievable score with a p-value no greater than the one given, or `None` if
+    /// even the best possible score isn't that unlikely.
+    pub fn score_threshold(&self, p_value: f64) -> Option<f64> {
+        let tail = &self.tail_probabilities;
+        (0..tail.len())
+            .find(|i| tail[*i] <= p_value && tail[*i] > tail.get(i + 1).copied().unwrap_or(0.0))
+            .map(|i| (i as i64 + self.min_score) as f64 / SCORE_RESOLUTION)
+    }
+}
+
+pub struct PwmHit {
+    pub start: usize,
+    pub strand: Strand,
+    pub score: f64,
+    pub p_value: f64,
+}
+
+pub fn scan(pwm: &Pwm, seq: &[u8], max_p_value: f64) -> Vec<PwmHit> {
+    let mut hits = vec![];
+    if pwm.width() > seq.len() {
+        return hits;
+    }
+    for (start, window) in seq.windows(pwm.width()).enumerate() {
+        for strand in [Strand::Forward, Strand::Reverse] {
+            let score = match strand {
+                Strand::Forward => pwm.score(window),
+                Strand::Reverse => pwm.score(&revcomp(window)),
+            };
+            let Some(score) = score else {
+                continue;
+            };
+            let p_value = pwm.p_value(score);
+            if p_value <= max_p_value {
+                hits.push(PwmHit {
+                    start,
+                    strand,
+                    score,
+                    p_value,
+                });
+            }
+        }
+    }
+    hits
+}
+
+pub fn read_pwms(path: &Path, pseudocount: f64) -> Result<Vec<Pwm>> {
+    if pseudocount <= 0.0 {
+        bail!("Pseudocount must be greater than zero");
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read motif file {}", path.display()))?;
+    let motifs =
+        parse_motifs(&text).with_context(|| format!("Invalid motif file {}", path.display()))?;
+    Ok(motifs.iter().map(|m| Pwm::new(m, pseudocount)).collect())
+}
+
+pub fn thresholds(pwms: &[Pwm], max_p_value: f64) -> String {
+    let mut output = vec!["motif\twidth\tmax_score\tthreshold".to_string()];
+    for pwm in pwms {
+        output.push(format!(
+            "{}\t{}\t{:.4}\t{}",
+            pwm.name,
+            pwm.width(),
+            pwm.max_score(),
+            crate::composition::format_optional(pwm.score_threshold(max_p_value))
+        ));
+    }
+    output.join("\n")
+}
+
+pub fn pwm_scan(
+    pwms: &[Pwm],
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    max_p_value: f64,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let offset = if use_0_based_coords { 0 } else { 1 };
+    let header = "motif\tstart\tend\tstrand\tscore\tp_value\tmatch";
+    let mut output = vec![];
+    if fasta.is_some() {
+        output.push(format!("id\t{header}"));
+    } else {
+        output.push(header.to_string());
+    }
+    for record in records {
+        iupac::confirm_valid_iupac(&record.seq)?;
+        let seq = record.seq.as_bytes();
+        for pwm in pwms {
+            for hit in scan(pwm, seq, max_p_value) {
+                let window = &seq[hit.start..hit.start + pwm.width()];
+                let matched = match hit.strand {
+                    Strand::Forward => window.to_vec(),
+                    Strand::Reverse => revcomp(window),
+                };
+                let line = format!(
+                    "{}\t{}\t{}\t{}\t{:.4}\t{:.4e}\t{}",
+                    pwm.name,
+                    hit.start + offset,
+                    hit.start + pwm.width(),
+                    hit.strand.symbol(),
+                    hit.score,
+                    hit.p_value,
+                    String::from_utf8_lossy(&matched)
+                );
+                if record.id.is_empty() {
+                    output.push(line);
+                } else {
+                    output.push(format!("{}\t{}", record.id, line));
+                }
+            }
+        }
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JASPAR: &str = ">MA0004.1 Arnt
+A  [ 4 19  0  0  0  0 ]
+C  [16  0 20  0  0  0 ]
+G  [ 0  1  0 20  0 20 ]
+T  [ 0  0  0  0 20  0 ]
+";
+
+    const MEME: &str = "MEME version 4
+
+ALPHABET= ACGT
+
+MOTIF MA0004.1 Arnt
+letter-probability matrix: alength= 4 w= 2 nsites= 20 E= 0
+  0.200000  0.800000  0.000000  0.000000
+  0.950000  0.000000  0.050000  0.000000
+";
+
+    const TRANSFAC: &str = "AC  MA0004.1
+XX
+ID  Arnt
+XX
+P0      A      C      G      T
+01      4     16      0      0      C
+02     19      0      1      0      A
+XX
+//
+";
+
+    #[test]
+    fn test_parse_jaspar() {
+        let motifs = parse_motifs(JASPAR).unwrap();
+        assert_eq!(motifs.len(), 1);
+        assert_eq!(motifs[0].name, "MA0004.1 Arnt");
+        assert_eq!(motifs[0].counts.len(), 6);
+        assert_eq!(motifs[0].counts[0], [4.0, 16.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_parse_headerless_jaspar() {
+        let motifs = parse_motifs("1 2\n3 4\n5 6\n7 8\n").unwrap();
+        assert_eq!(motifs[0].name, "motif_1");
+        assert_eq!(
+            motifs[0].counts,
+            vec![[1.0, 3.0, 5.0, 7.0], [2.0, 4.0, 6.0, 8.0]]
+        );
+    }
+
+    #[test]
+    fn test_parse_meme() {
+        let motifs = parse_motifs(MEME).unwrap();
+        assert_eq!(motifs[0].name, "MA0004.1 Arnt");
+        assert_eq!(
+            motifs[0].counts,
+            vec![[4.0, 16.0, 0.0, 0.0], [19.0, 0.0, 1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn test_parse_transfac() {
+        let motifs = parse_motifs(TRANSFAC).unwrap();
+        assert_eq!(motifs[0].name, "Arnt");
+        assert_eq!(
+            motifs[0].counts,
+            vec![[4.0, 16.0, 0.0, 0.0], [19.0, 0.0, 1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn test_invalid_matrix() {
+        assert!(parse_motifs(">x\nA [1 2]\nC [1]\nG [1 2]\nT [1 2]\n").is_err());
+        assert!(parse_motifs("").is_err());
+    }
+
+    #[test]
+    fn test_p_values() {
+        let motif = Motif {
+            name: "test".to_string(),
+            counts: vec![[10.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 10.0]],
+        };
+        let pwm = Pwm::new(&motif, 0.0001);
+        // Only AT has the best score, which a random dinucleotide has a 1 in 16 chance of.
+        let best = pwm.score(b"AT").unwrap();
+        assert!((best - pwm.max_score()).abs() < 1e-9);
+        assert!((pwm.p_value(best) - 1.0 / 16.0).abs() < 1e-9);
+        assert_eq!(pwm.p_value(f64::MIN / 2.0), 1.0);
+        assert_eq!(
+            pwm.score_threshold(1.0 / 16.0),
+            Some((best * 100.0).round() / 100.0)
+        );
+        assert_eq!(pwm.score_threshold(0.01), None);
+        assert_eq!(pwm.score(b"AN"), None);
+    }
+
+    #[test]
+    fn test_scan_both_strands() {
+        let pwms: Vec<Pwm> = parse_motifs(JASPAR)
+            .unwrap()
+            .iter()
+            .map(|m| Pwm::new(m, 1.0))
+            .collect();
+        // CACGTG is palindromic, so the site is found on both strands.
+        let hits = scan(&pwms[0], b"TTTTCACGTGTTTT", 0.001);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].start, 4);
+        assert_eq!(hits[1].strand, Strand::Reverse);
+        assert!(hits[0].p_value < 0.001);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::fasta;
+use crate::iupac;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+const MAX_MICROSATELLITE_PERIOD: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RepeatKind {
+    Homopolymer,
+    Microsatellite,
+    Tandem,
+}
+
+impl RepeatKind {
+    fn name(&self) -> &'static str {
+        match self {
+            RepeatKind::Homopolymer => "homopolymer",
+            RepeatKind::Microsatellite => "microsatellite",
+            RepeatKind::Tandem => "tandem",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Repeat {
+    pub kind: RepeatKind,
+    /// Zero-based start. For circular sequences, the repeat may extend past the origin.
+    pub start: usize,
+    pub length: usize,
+    pub period: usize,
+    pub unit: String,
+}
+
+impl Repeat {
+    pub fn copies(&self) -> f64 {
+        self.length as f64 / self.period as f64
+    }
+}
+
+pub struct RepeatOptions {
+    pub min_homopolymer: usize,
+    pub min_microsatellite: usize,
+    pub min_tandem: usize,
+    pub max_period: usize,
+    pub circular: bool,
+}
+
+/// Whether two bases are the same unambiguous base, ignoring case.
+fn same_base(a: u8, b: u8) -> bool {
+    let is_unambiguous = matches!(a.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T' | b'U');
+    is_unambiguous && a.eq_ignore_ascii_case(&b)
+}
+
+/// Whether a repeat unit is itself made of a shorter repeated unit (e.g. ATAT is ATx2), in which
+/// case the region will already be reported with the shorter period.
+fn is_primitive(unit: &[u8]) -> bool {
+    (1..unit.len())
+        .filter(|p| unit.len().is_multiple_of(*p))
+        .all(|p| !(0..unit.len() - p).all(|i| same_base(unit[i], unit[i + p])))
+}
+
+/// Finds maximal stretches of a sequence that are periodic with the given period, returned as
+/// (start, length) pairs. A stretch must contain at least one full copy of the unit plus one
+/// base, since shorter stretches are trivially periodic.
+fn periodic_runs(seq: &[u8], period: usize, circular: bool) -> Vec<(usize, usize)> {
+    let n = seq.len();
+    if period >= n {
+        return vec![];
+    }
+    let comparisons = if circular { n } else { n - period };
+    let matches: Vec<bool> = (0..comparisons)
+        .map(|i| same_base(seq[i], seq[(i + period) % n]))
+        .collect();
+
+    // For circular sequences, begin scanning just after a mismatch so that no run is split
+    // across the origin.
+    let offset = if circular {
+        match matches.iter().position(|m| !m) {
+            Some(mismatch) => mismatch + 1,
+            None => return vec![(0, n)],
+        }
+    } else {
+        0
+    };
+
+    let mut runs = vec![];
+    let mut run_start = None;
+    for k in 0..=comparisons {
+        let i = (k + offset) % comparisons;
+        let is_match = k < comparisons && matches[i];
+        match (is_match, run_start) {
+            (true, None) => run_start = Some(k),
+            (false, Some(start)) => {
+                let length = (k - start + period).min(n);
+                runs.push(((start + offset) % n, length));
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    runs
+}
+
+pub fn find_repeats(seq: &[u8], opts: &RepeatOptions) -> Vec<Repeat> {
+    let n = seq.len();
+    let mut repeats = vec![];
+    for period in 1..=opts.max_period {
+        let (kind, min_length) = match period {
+            1 => (RepeatKind::Homopolymer, opts.min_homopolymer),
+            2..=MAX_MICROSATELLITE_PERIOD => (RepeatKind::Microsatellite, opts.min_microsatellite),
+            _ => (RepeatKind::Tandem, opts.min_tandem),
+        };
+        for (start, length) in periodic_runs(seq, period, opts.circular) {
+            // Every repeat needs at least two full copies of its unit.
+            if length < min_length || length < 2 * period {
+                continue;
+            }
+            let unit: Vec<u8> = (start..start + period).map(|i| seq[i % n]).collect();
+            if !is_primitive(&unit) {
+                continue;
+            }
+            repeats.push(Repeat {
+                kind,
+                start,
+                length,
+                period,
+                unit: String::from_utf8_lossy(&unit).to_ascii_uppercase(),
+            });
+        }
+    }
+    repeats.sort_by_key(|r| (r.start, r.period));
+    repeats
+}
+
+pub fn repeats(
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    opts: RepeatOptions,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    if opts.max_period == 0 {
+        bail!("Maximum period must be at least 1");
+    }
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let offset = if use_0_based_coords { 0 } else { 1 };
+    let mut output = vec![];
+    let header = "type\tstart\tend\tlength\tperiod\tunit\tcopies";
+    if fasta.is_some() {
+        output.push(format!("id\t{header}"));
+    } else {
+        output.push(header.to_string());
+    }
+    for record in records {
+        iupac::confirm_valid_iupac(&record.seq)?;
+        let n = record.seq.len();
+        for repeat in find_repeats(record.seq.as_bytes(), &opts) {
+            let end = (repeat.start + repeat.length - 1) % n + 1;
+            let line = format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{:.1}",
+                repeat.kind.name(),
+                repeat.start + offset,
+                end,
+                repeat.length,
+                repeat.period,
+                repeat.unit,
+                repeat.copies()
+            );
+            if record.id.is_empty() {
+                output.push(line);
+            } else {
+                output.push(format!("{}\t{}", record.id, line));
+            }
+        }
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_homopolymer() {
+        let opts = RepeatOptions {
+            min_homopolymer: 5,
+            min_microsatellite: 8,
+            min_tandem: 20,
+            max_period: 20,
+            circular: false,
+        };
+        let repeats = find_repeats(b"GCAAAAAAGC", &opts);
+        assert_eq!(
+            repeats,
+            vec![Repeat {
+                kind: RepeatKind::Homopolymer,
+                start: 2,
+                length: 6,
+                period: 1,
+                unit: "A".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_microsatellite_not_reported_with_multiple_periods() {
+        let opts = RepeatOptions {
+            min_homopolymer: 5,
+            min_microsatellite: 8,
+            min_tandem: 20,
+            max_period: 20,
+            circular: false,
+        };
+        let repeats = find_repeats(b"GCCACACACACAGG", &opts);
+        assert_eq!(repeats.len(), 1);
+        assert_eq!(repeats[0].kind, RepeatKind::Microsatellite);
+        assert_eq!(repeats[0].unit, "CA");
+        assert_eq!(repeats[0].start, 2);
+        assert_eq!(repeats[0].length, 10);
+    }
+
+    #[test]
+    fn test_tandem_repeat() {
+        let unit = "ACGTTGCATG";
+        let seq = format!("CC{unit}{unit}{unit}GG");
+        let opts = RepeatOptions {
+            min_homopolymer: 5,
+            min_microsatellite: 8,
+            min_tandem: 20,
+            max_period: 20,
+            circular: false,
+        };
+        let repeats = find_repeats(seq.as_bytes(), &opts);
+        assert_eq!(repeats.len(), 1);
+        assert_eq!(repeats[0].kind, RepeatKind::Tandem);
+        assert_eq!(repeats[0].period, 10);
+        assert_eq!(repeats[0].copies(), 3.0);
+    }
+
+    #[test]
+    fn test_repeat_across_origin() {
+        let seq = b"AAAGCTGCAAA";
+        let mut opts = RepeatOptions {
+            min_homopolymer: 5,
+            min_microsatellite: 8,
+            min_tandem: 20,
+            max_period: 20,
+            circular: false,
+        };
+        assert!(find_repeats(seq, &opts).is_empty());
+        opts.circular = true;
+        let repeats = find_repeats(seq, &opts);
+        assert_eq!(repeats.len(), 1);
+        assert_eq!(repeats[0].start, 8);
+        assert_eq!(repeats[0].length, 6);
+    }
+
+    #[test]
+    fn test_ambiguous_bases_are_not_repeats() {
+        let opts = RepeatOptions {
+            min_homopolymer: 5,
+            min_microsatellite: 8,
+            min_tandem: 20,
+            max_period: 20,
+            circular: false,
+        };
+        assert!(find_repeats(b"ACNNNNNNNNGT", &opts).is_empty());
+    }
+
+    #[test]
+    fn test_repeats_output() {
+        let opts = RepeatOptions {
+            min_homopolymer: 5,
+            min_microsatellite: 8,
+            min_tandem: 20,
+            max_period: 20,
+            circular: false,
+        };
+        let actual = repeats(vec!["GCAAAAAAGC".to_string()], None, opts, false).unwrap();
+        assert_eq!(
+            actual,
+            "type\tstart\tend\tlength\tperiod\tunit\tcopies\nhomopolymer\t3\t8\t6\t1\tA\t6.0"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use crate::circular;
+use crate::digest::Strand;
+use crate::fasta;
+use crate::iupac;
+use anyhow::{bail, Context, Result};
+use bio::alphabets::dna::revcomp;
+use regex::bytes::RegexBuilder;
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+pub struct Hit {
+    /// Zero-based start on the forward strand. For circular sequences, the hit may extend past
+    /// the origin.
+    pub start: usize,
+    pub length: usize,
+    pub strand: Strand,
+    pub mismatches: usize,
+    /// The matching bases, read 5' to 3' on the strand of the hit.
+    pub matched: String,
+}
+
+pub enum Pattern {
+    Iupac {
+        motif: Vec<u8>,
+        max_mismatches: usize,
+    },
+    Regex(String),
+}
+
+/// Counts positions where the motif doesn't match, giving up once there are too many.
+fn count_mismatches(motif: &[u8], window: &[u8], max_mismatches: usize) -> Option<usize> {
+    let mut mismatches = 0;
+    for (p, s) in motif.iter().zip(window) {
+        if !iupac::matches(*p, *s) {
+            mismatches += 1;
+            if mismatches > max_mismatches {
+                return None;
+            }
+        }
+    }
+    Some(mismatches)
+}
+
+fn find_iupac(seq: &[u8], motif: &[u8], max_mismatches: usize, circular: bool) -> Vec<Hit> {
+    let n = seq.len();
+    if motif.is_empty() || motif.len() > n {
+        return vec![];
+    }
+    let scanned = if circular {
+        circular::extend_across_origin(seq, motif.len() - 1)
+    } else {
+        seq.to_vec()
+    };
+    let rc_motif = revcomp(motif);
+    let mut patterns = vec![(Strand::Forward, motif.to_vec())];
+    // A palindromic motif would find every hit twice.
+    if !rc_motif.eq_ignore_ascii_case(motif) {
+        patterns.push((Strand::Reverse, rc_motif));
+    }
+    let mut hits = vec![];
+    for (start, window) in scanned.windows(motif.len()).take(n).enumerate() {
+        for (strand, pattern) in &patterns {
+            if let Some(mismatches) = count_mismatches(pattern, window, max_mismatches) {
+                let bases = match strand {
+                    Strand::Forward => window.to_vec(),
+                    Strand::Reverse => revcomp(window),
+                };
+                hits.push(Hit {
+                    start,
+                    length: motif.len(),
+                    strand: *strand,
+                    mismatches,
+                    matched: String::from_utf8_lossy(&bases).to_string(),
+                });
+            }
+        }
+    }
+    hits
+}
+
+/// Finds every match of a regular expression on both strands, including overlapping matches.
+/// Reverse strand hits are found by searching the reverse complement.
+fn find_regex(seq: &[u8], pattern: &str, circular: bool) -> Result<Vec<Hit>> {
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .unicode(false)
+        .build()
+        .with_context(|| format!("Invalid regular expression: {pattern}"))?;
+    let n = seq.len();
+    let mut hits = vec![];
+    for strand in [Strand::Forward, Strand::Reverse] {
+        let stranded = match strand {
+            Strand::Forward => seq.to_vec(),
+            Strand::Reverse => revcomp(seq),
+        };
+        let scanned = if circular && n > 0 {
+            circular::extend_across_origin(&stranded, n - 1)
+        } else {
+            stranded
+        };
+        let mut position = 0;
+        while position < n {
+            let Some(m) = regex.find_at(&scanned, position) else {
+                break;
+            };
+            if m.start() >= n {
+                break;
+            }
+            position = m.start() + 1;
+            // A match can't be longer than the circle it's found in, so one that is gets
+            // searched for again within a single turn.
+            let m = if m.len() > n {
+                match regex.find_at(&scanned[..m.start() + n], m.start()) {
+                    Some(shorter) if shorter.start() == m.start() => shorter,
+                    _ => continue,
+                }
+            } else {
+                m
+            };
+            if m.is_empty() {
+                continue;
+            }
+            let start = match strand {
+                Strand::Forward => m.start(),
+                Strand::Reverse => (2 * n - m.end()) % n,
+            };
+            hits.push(Hit {
+                start,
+                length: m.len(),
+                strand,
+                mismatches: 0,
+                matched: String::from_utf8_lossy(m.as_bytes()).to_string(),
+            });
+        }
+    }
+    Ok(hits)
+}
+
+pub fn find_hits(seq: &[u8], pattern: &Pattern, circular: bool) -> Result<Vec<Hit>> {
+    let mut hits = match pattern {
+        Pattern::Iupac {
+            motif,
+            max_mismatches,
+        } => find_iupac(seq, motif, *max_mismatches, circular),
+        Pattern::Regex(regex) => find_regex(seq, regex, circular)?,
+    };
+    hits.sort_by_key(|hit| (hit.start, hit.strand == Strand::Reverse));
+    Ok(hits)
+}
+
+pub fn find(
+    pattern: Pattern,
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    circular: bool,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    if let Pattern::Iupac { motif, .. } = &pattern {
+        if motif.is_empty() {
+            bail!("Motif is empty");
+        }
+        iupac::confirm_valid_iupac(&String::from_utf8_lossy(motif))
+            .context("Invalid motif (use --regex for regular expressions)")?;
+    }
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let offset = if use_0_based_coords { 0 } else { 1 };
+    let header = "start\tend\tstrand\tmismatches\tmatch";
+    let mut output = vec![];
+    if fasta.is_some() {
+        output.push(format!("id\t{header}"));
+    } else {
+        output.push(header.to_string());
+    }
+    for record in records {
+        iupac::confirm_valid_iupac(&record.seq)?;
+        let n = record.seq.len();
+        for hit in find_hits(record.seq.as_bytes(), &pattern, circular)? {
+            let line = format!(
+                "{}\t{}\t{}\t{}\t{}",
+                hit.start + offset,
+                (hit.start + hit.length - 1) % n + 1,
+                hit.strand.symbol(),
+                hit.mismatches,
+                hit.matched
+            );
+            if record.id.is_empty() {
+                output.push(line);
+            } else {
+                output.push(format!("{}\t{}", record.id, line));
+            }
+        }
+    }
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn iupac(motif: &str, max_mismatches: usize) -> Pattern {
+        Pattern::Iupac {
+            motif: motif.as_bytes().to_vec(),
+            max_mismatches,
+        }
+    }
+
+    #[test]
+    fn test_finds_both_strands() {
+        let hits = find_hits(b"AACCGTTTTTACGGTT", &iupac("ACCG", 0), false).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].start, hits[0].strand), (1, Strand::Forward));
+        assert_eq!((hits[1].start, hits[1].strand), (11, Strand::Reverse));
+        assert_eq!(hits[1].matched, "ACCG");
+    }
+
+    #[test]
+    fn test_palindrome_reported_once() {
+        let hits = find_hits(b"TTGAATTCTT", &iupac("GAATTC", 0), false).unwrap();
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[test]
+    fn test_degenerate_motif_with_mismatches() {
+        let seq = b"TTTGGATTTT";
+        assert!(find_hits(seq, &iupac("GRAC", 0), false).unwrap().is_empty());
+        let hits = find_hits(seq, &iupac("GRAC", 1), false).unwrap();
+        assert_eq!(hits[0].start, 3);
+        assert_eq!(hits[0].mismatches, 1);
+        assert_eq!(hits[0].matched, "GGAT");
+    }
+
+    #[test]
+    fn test_circular_hit() {
+        let seq = b"CGTTTTTTAC";
+        assert!(find_hits(seq, &iupac("ACCG", 0), false).unwrap().is_empty());
+        let hits = find_hits(seq, &iupac("ACCG", 0), true).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].start, 8);
+    }
+
+    #[test]
+    fn test_regex_on_both_strands() {
+        let seq = b"ATGAAATAGCCCCTAAC";
+        let hits = find_hits(seq, &Pattern::Regex("ATG(...)*?TA[AG]".to_string()), false).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].matched, "ATGAAATAG");
+        // TTAGGGG on the reverse strand is CCCCTAA on the forward strand.
+        let hits = find_hits(seq, &Pattern::Regex("ttag+".to_string()), false).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].start, hits[0].strand), (9, Strand::Reverse));
+        assert_eq!(hits[0].matched, "TTAGGGG");
+    }
+
+    #[test]
+    fn test_circular_regex_is_no_longer_than_sequence() {
+        let hits = find_hits(b"AAAA", &Pattern::Regex("A+".to_string()), true).unwrap();
+        assert_eq!(hits.len(), 4);
+        assert!(hits.iter().all(|hit| hit.matched == "AAAA"));
+    }
+
+    #[test]
+    fn test_find_output() {
+        let actual = find(
+            iupac("ACCG", 0),
+            vec!["AACCG TT".to_string()],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            actual,
+            "start\tend\tstrand\tmismatches\tmatch\n2\t5\t+\t0\tACCG"
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use crate::digest::{self, Enzyme};
+use crate::fasta;
+use crate::repeats::{self, RepeatKind, RepeatOptions};
+use crate::{compute_gc_content, compute_windowed_gc_content};
+use anyhow::{bail, Result};
+use bio::alphabets::dna::complement;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Repeats with longer units are rarely a problem for synthesis that isn't already flagged as a
+/// hairpin or as extreme GC, and searching for them is slow on long sequences.
+const MAX_REPEAT_PERIOD: usize = 50;
+
+/// Hairpins need a loop of at least three bases to form.
+const MIN_HAIRPIN_LOOP: usize = 3;
+
+pub struct SynthesisOptions {
+    pub min_gc: f64,
+    pub max_gc: f64,
+    pub window: usize,
+    pub min_window_gc: f64,
+    pub max_window_gc: f64,
+    pub max_homopolymer: usize,
+    pub max_repeat: usize,
+    pub min_hairpin_stem: usize,
+    pub max_hairpin_loop: usize,
+    pub forbidden_enzymes: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Hairpin {
+    /// Zero-based start of the 5' arm of the stem.
+    pub start: usize,
+    pub stem: usize,
+    pub loop_length: usize,
+}
+
+impl Hairpin {
+    /// Zero-based, exclusive end of the 3' arm of the stem.
+    pub fn end(&self) -> usize {
+        self.start + 2 * self.stem + self.loop_length
+    }
+}
+
+/// Finds inverted repeats that could fold back on themselves, with stems of at least `min_stem`
+/// perfectly paired bases and loops of up to `max_loop` bases. Each stem is extended as far as
+/// it will go in both directions, so a hairpin is only reported once. The sequence must be
+/// uppercase DNA.
+pub fn find_hairpins(seq: &[u8], min_stem: usize, max_loop: usize) -> Vec<Hairpin> {
+    let n = seq.len();
+    if min_stem == 0 || n < 2 * min_stem + MIN_HAIRPIN_LOOP {
+        return vec![];
+    }
+    let mut kmers: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (i, kmer) in seq.windows(min_stem).enumerate() {
+        kmers.entry(kmer).or_default().push(i);
+    }
+    let pairs = |a: u8, b: u8| matches!(a, b'A' | b'C' | b'G' | b'T') && complement(a) == b;
+
+    let mut hairpins = vec![];
+    for (i, kmer) in seq.windows(min_stem).enumerate() {
+        let target: Vec<u8> = kmer.iter().rev().map(|b| complement(*b)).collect();
+        let Some(positions) = kmers.get(target.as_slice()) else {
+            continue;
+        };
+        for &j in positions {
+            let right_end = j + min_stem;
+            if j < i + min_stem + MIN_HAIRPIN_LOOP || j > i + min_stem + max_loop {
+                continue;
+            }
+            // Only start from the outermost pair, so the same stem isn't reported repeatedly.
+            if i > 0 && right_end < n && pairs(seq[i - 1], seq[right_end]) {
+                continue;
+            }
+            let mut stem = min_stem;
+            while right_end - i >= 2 * (stem + 1) + MIN_HAIRPIN_LOOP
+                && pairs(seq[i + stem], seq[right_end - stem - 1])
+            {
+                stem += 1;
+            }
+            hairpins.push(Hairpin {
+                start: i,
+                stem,
+                loop_length: right_end - i - 2 * stem,
+            });
+        }
+    }
+    hairpins
+}
+
+struct Check {
+    name: &'static str,
+    passed: bool,
+    details: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, problems: Vec<String>) -> Check {
+        Check {
+            name,
+            passed: problems.is_empty(),
+            details: problems,
+        }
+    }
+}
+
+fn format_region(start: usize, end: usize, use_0_based_coords: bool) -> String {
+    if use_0_based_coords {
+        format!("{start}-{end}")
+    } else {
+        format!("{}-{end}", start + 1)
+    }
+}
+
+/// Merges overlapping windows whose GC content is out of bounds in the same direction into a
+/// single region, reported with its most extreme GC content.
+fn check_window_gc(seq: &str, opts: &SynthesisOptions, use_0_based_coords: bool) -> Result<Check> {
+    if seq.len() < opts.window {
+        return Ok(Check {
+            name: "window_gc",
+            passed: true,
+            details: vec![format!(
+                "skipped (shorter than the {} bp window)",
+                opts.window
+            )],
+        });
+    }
+    let mut regions: Vec<(usize, usize, bool, f64)> = vec![];
+    for window in compute_windowed_gc_content(seq, opts.window, 1)? {
+        let gc = window.gc_content();
+        let is_high = if gc > opts.max_window_gc {
+            true
+        } else if gc < opts.min_window_gc {
+            false
+        } else {
+            continue;
+        };
+        match regions.last_mut() {
+            Some((_, end, high, extreme)) if *high == is_high && window.start <= *end => {
+                *end = window.end;
+                *extreme = if is_high {
+                    extreme.max(gc)
+                } else {
+                    extreme.min(gc)
+                };
+            }
+            _ => regions.push((window.start, window.end, is_high, gc)),
+        }
+    }
+    let problems = regions
+        .iter()
+        .map(|(start, end, is_high, extreme)| {
+            format!(
+                "{} GC {:.4} at {}",
+                if *is_high { "high" } else { "low" },
+                extreme,
+                format_region(*start, *end, use_0_based_coords)
+            )
+        })
+        .collect();
+    Ok(Check::new("window_gc", problems))
+}
+
+fn check_sequence(
+    seq: &str,
+    opts: &SynthesisOptions,
+    enzymes: &[&'static Enzyme],
+    use_0_based_coords: bool,
+) -> Result<Vec<Check>> {
+    let gc = compute_gc_content(vec![seq.to_string()])? as f64;
+    let gc_check = Check {
+        name: "gc",
+        passed: gc >= opts.min_gc && gc <= opts.max_gc,
+        details: vec![format!("{:.4}", gc)],
+    };
+
+    let window_gc_check = check_window_gc(seq, opts, use_0_based_coords)?;
+
+    let seq = seq.to_ascii_uppercase().replace("U", "T").into_bytes();
+    let repeat_opts = RepeatOptions {
+        min_homopolymer: opts.max_homopolymer + 1,
+        min_microsatellite: opts.max_repeat + 1,
+        min_tandem: opts.max_repeat + 1,
+        max_period: MAX_REPEAT_PERIOD,
+        circular: false,
+    };
+    let (homopolymers, other_repeats): (Vec<_>, Vec<_>) = repeats::find_repeats(&seq, &repeat_opts)
+        .into_iter()
+        .partition(|r| r.kind == RepeatKind::Homopolymer);
+    let describe_repeat = |r: &repeats::Repeat| {
+        format!(
+            "{}x{:.1} at {}",
+            r.unit,
+            r.copies(),
+            format_region(r.start, r.start + r.length, use_0_based_coords)
+        )
+    };
+    let homopolymer_check = Check::new(
+        "homopolymers",
+        homopolymers.iter().map(describe_repeat).collect(),
+    );
+    let repeat_check = Check::new(
+        "repeats",
+        other_repeats.iter().map(describe_repeat).collect(),
+    );
+
+    let hairpin_check = Check::new(
+        "hairpins",
+        find_hairpins(&seq, opts.min_hairpin_stem, opts.max_hairpin_loop)
+            .iter()
+            .map(|h| {
+                format!(
+                    "{} bp stem, {} nt loop at {}",
+                    h.stem,
+                    h.loop_length,
+                    format_region(h.start, h.end(), use_0_based_coords)
+                )
+            })
+            .collect(),
+    );
+
+    let mut sites = vec![];
+    for enzyme in enzymes {
+        for (position, strand) in digest::find_recognition_sites(enzyme, &seq, false) {
+            sites.push(format!(
+                "{} ({}) at {}",
+                enzyme.name,
+                strand.symbol(),
+                format_region(position, position + enzyme.site.len(), use_0_based_coords)
+            ));
+        }
+    }
+    let site_check = Check::new("restriction_sites", sites);
+
+    Ok(vec![
+        gc_check,
+        window_gc_check,
+        homopolymer_check,
+        repeat_check,
+        hairpin_check,
+        site_check,
+    ])
+}
+
+fn format_report(checks: &[Check]) -> String {
+    let status = |passed: bool| if passed { "PASS" } else { "FAIL" };
+    let mut output = vec!["check\tresult\tdetails".to_string()];
+    for check in checks {
+        output.push(format!(
+            "{}\t{}\t{}",
+            check.name,
+            status(check.passed),
+            check.details.join("; ")
+        ));
+    }
+    let passed = checks.iter().all(|check| check.passed);
+    output.push(format!("overall\t{}\t", status(passed)));
+    output.join("\n")
+}
+
+pub fn synth_check(
+    seqs: Vec<String>,
+    fasta: Option<&Path>,
+    opts: SynthesisOptions,
+    use_0_based_coords: bool,
+) -> Result<String> {
+    if opts.min_gc > opts.max_gc || opts.min_window_gc > opts.max_window_gc {
+        bail!("Minimum GC content must not be greater than the maximum");
+    }
+    if opts.window == 0 || opts.min_hairpin_stem == 0 {
+        bail!("Window size and minimum hairpin stem length must be greater than zero");
+    }
+    let enzymes = opts
+        .forbidden_enzymes
+        .iter()
+        .map(|name| digest::find_enzyme(name))
+        .collect::<Result<Vec<_>>>()?;
+    let records = fasta::records_or_sequence(seqs, fasta)?;
+    let mut output = vec![];
+    for record in records {
+        let report = format_report(&check_sequence(
+            &record.seq,
+            &opts,
+            &enzymes,
+            use_0_based_coords,
+        )?);
+        if record.id.is_empty() {
+            output.push(report);
+        } else {
+            output.push(format!(">{}\n{}", record.id, report));
+        }
+    }
+    Ok(output.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_hairpins() {
+        // ACCGTG pairs with CACGGT across a 4 nt loop; the flanking Ts can't pair with each other.
+        assert_eq!(
+            find_hairpins(b"TTACCGTGAAAACACGGTTT", 6, 20),
+            vec![Hairpin {
+                start: 2,
+                stem: 6,
+                loop_length: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_hairpin_stem_is_extended() {
+        let hairpins = find_hairpins(b"ACCGTGCTTTTGCACGGT", 6, 20);
+        assert_eq!(
+            hairpins,
+            vec![Hairpin {
+                start: 0,
+                stem: 7,
+                loop_length: 4
+            }]
+        );
+        assert_eq!(hairpins[0].end(), 18);
+    }
+
+    #[test]
+    fn test_hairpin_loop_too_long() {
+        assert!(find_hairpins(b"TTACCGTGAAAACACGGTTT", 6, 3).is_empty());
+    }
+
+    #[test]
+    fn test_synth_check_passes() {
+        let seq = "ATGGCTAGCAAAGGAGAAGAACTTTTCACTGGAGTTGTCCCAATTCTTGTTGAATTAGATGGTGATGTTAATGGGCACAAATTTTCTGTC";
+        let opts = SynthesisOptions {
+            min_gc: 0.25,
+            max_gc: 0.65,
+            window: 20,
+            min_window_gc: 0.2,
+            max_window_gc: 0.8,
+            max_homopolymer: 8,
+            max_repeat: 20,
+            min_hairpin_stem: 6,
+            max_hairpin_loop: 20,
+            forbidden_enzymes: vec!["BsaI".to_string()],
+        };
+        let report = synth_check(vec![seq.to_string()], None, opts, false).unwrap();
+        assert!(report.ends_with("overall\tPASS\t"), "{report}");
+    }
+
+    #[test]
+    fn test_synth_check_failures() {
+        let seq = "ATGGCTAGCAAAAAAAAAAAGGAGAAGAACTTTTCACTGGTCTCGTTGTCCCAATTCTTGTTGAATTAG";
+        let opts = SynthesisOptions {
+            min_gc: 0.25,
+            max_gc: 0.65,
+            window: 20,
+            min_window_gc: 0.2,
+            max_window_gc: 0.8,
+            max_homopolymer: 8,
+            max_repeat: 20,
+            min_hairpin_stem: 6,
+            max_hairpin_loop: 20,
+            forbidden_enzymes: vec!["BsaI".to_string()],
+        };
+        let report = synth_check(vec![seq.to_string()], None, opts, false).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[3], "homopolymers\tFAIL\tAx11.0 at 10-20");
+        assert_eq!(lines[6], "restriction_sites\tFAIL\tBsaI (+) at 39-44");
+        assert_eq!(lines[7], "overall\tFAIL\t");
+    }
+}
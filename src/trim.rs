@@ -0,0 +1,275 @@
+use crate::fasta;
+use crate::iupac;
+use crate::{run_alignment, AlignmentCommand};
+use anyhow::{bail, Context, Result};
+use bio::alignment::pairwise::Aligner;
+use bio::alignment::AlignmentOperation;
+use bio::io::fastq;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Appended to reads so that adapters can hang off of their 3' end. Padding scores zero against
+/// every base, so an adapter is free to extend past the end of a read.
+const PADDING: u8 = 0;
+const GAP_OPEN: i32 = -2;
+const GAP_EXTEND: i32 = -1;
+const PHRED_OFFSET: u8 = 33;
+
+pub struct TrimOptions {
+    pub adapters: Vec<Vec<u8>>,
+    pub error_rate: f64,
+    pub min_overlap: usize,
+    pub quality_cutoff: Option<u8>,
+    pub min_length: usize,
+}
+
+fn score(a: u8, b: u8) -> i32 {
+    if a == PADDING || b == PADDING {
+        0
+    } else if iupac::matches(a, b) {
+        1
+    } else {
+        -1
+    }
+}
+
+/// Length of a read after trimming low-quality bases from its 3' end, using the same algorithm
+/// as BWA and cutadapt: the read is cut where the sum of (cutoff - quality) over the bases after
+/// the cut is greatest. This tolerates the odd high-quality base among low-quality ones.
+pub fn quality_trimmed_length(qual: &[u8], cutoff: u8) -> usize {
+    let mut sum: i64 = 0;
+    let mut best = 0;
+    let mut length = qual.len();
+    for (i, q) in qual.iter().enumerate().rev() {
+        sum += cutoff as i64 - (q.saturating_sub(PHRED_OFFSET)) as i64;
+        if sum < 0 {
+            break;
+        }
+        if sum > best {
+            best = sum;
+            length = i;
+        }
+    }
+    length
+}
+
+/// Where an adapter begins in a read, if it aligns there within the error rate. The adapter is
+/// aligned semiglobally (all of it, to part of the read), and may run past the 3' end of the
+/// read, in which case only the part that overlaps the read counts. Errors are mismatches and
+/// gaps within the overlap.
+pub fn find_adapter(
+    read: &[u8],
+    adapter: &[u8],
+    aligner: &mut Aligner<impl Fn(u8, u8) -> i32>,
+    error_rate: f64,
+    min_overlap: usize,
+) -> Option<usize> {
+    let padded = [read, &vec![PADDING; adapter.len()]].concat();
+    let alignment = run_alignment(&AlignmentCommand::Semiglobal, aligner, adapter, &padded);
+    if alignment.ystart >= read.len() {
+        return None;
+    }
+    let (mut x, mut y) = (alignment.xstart, alignment.ystart);
+    let mut overlap = 0;
+    let mut errors = 0;
+    for op in &alignment.operations {
+        match op {
+            AlignmentOperation::Match | AlignmentOperation::Subst => {
+                if y < read.len() {
+                    overlap += 1;
+                    if !iupac::matches(adapter[x], read[y]) {
+                        errors += 1;
+                    }
+                }
+                x += 1;
+                y += 1;
+            }
+            AlignmentOperation::Ins => {
+                if y < read.len() {
+                    overlap += 1;
+                    errors += 1;
+                }
+                x += 1;
+            }
+            AlignmentOperation::Del => {
+                if y < read.len() {
+                    errors += 1;
+                }
+                y += 1;
+            }
+            AlignmentOperation::Xclip(_) | AlignmentOperation::Yclip(_) => {}
+        }
+    }
+    if overlap >= min_overlap && errors as f64 <= error_rate * overlap as f64 {
+        Some(alignment.ystart)
+    } else {
+        None
+    }
+}
+
+/// Length of a read after removing the earliest adapter found in it, and everything after.
+fn adapter_trimmed_length(
+    read: &[u8],
+    aligner: &mut Aligner<impl Fn(u8, u8) -> i32>,
+    opts: &TrimOptions,
+) -> usize {
+    opts.adapters
+        .iter()
+        .filter_map(|adapter| {
+            find_adapter(read, adapter, aligner, opts.error_rate, opts.min_overlap)
+        })
+        .min()
+        .unwrap_or(read.len())
+}
+
+fn is_fastq(path: &Path) -> Result<bool> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open reads {}", path.display()))?;
+    let mut first_line = String::new();
+    BufReader::new(file).read_line(&mut first_line)?;
+    Ok(first_line.starts_with('@'))
+}
+
+/// Quality trims (for FASTQ) and then adapter trims each read, and writes the reads in the same
+/// format as they're processed, leaving out any that end up shorter than the minimum length.
+pub fn trim(reads: &Path, opts: TrimOptions, out: &mut impl Write) -> Result<()> {
+    if opts.adapters.is_empty() && opts.quality_cutoff.is_none() {
+        bail!("Give at least one adapter or a quality cutoff");
+    }
+    if !(0.0..1.0).contains(&opts.error_rate) {
+        bail!("Error rate must be at least 0 and less than 1");
+    }
+    if opts.min_overlap == 0 {
+        bail!("Minimum overlap must be at least 1");
+    }
+    for adapter in &opts.adapters {
+        if adapter.is_empty() {
+            bail!("Adapter is empty");
+        }
+        iupac::confirm_valid_iupac(&String::from_utf8_lossy(adapter))?;
+    }
+    let mut aligner = Aligner::new(GAP_OPEN, GAP_EXTEND, score);
+    if !is_fastq(reads)? {
+        if opts.quality_cutoff.is_some() {
+            bail!("Quality trimming needs a FASTQ file");
+        }
+        for record in fasta::read_records(reads)? {
+            let length = adapter_trimmed_length(record.seq.as_bytes(), &mut aligner, &opts);
+            if length >= opts.min_length {
+                writeln!(out, ">{}\n{}", record.id, &record.seq[..length])?;
+            }
+        }
+        return Ok(());
+    }
+    let reader = fastq::Reader::from_file(reads)
+        .with_context(|| format!("Failed to open FASTQ file {}", reads.display()))?;
+    let mut writer = fastq::Writer::new(out);
+    for record in reader.records() {
+        let record = record.context("Failed to parse FASTQ record")?;
+        let mut length = record.seq().len();
+        if let Some(cutoff) = opts.quality_cutoff {
+            length = quality_trimmed_length(record.qual(), cutoff);
+        }
+        length = adapter_trimmed_length(&record.seq()[..length], &mut aligner, &opts);
+        if length >= opts.min_length {
+            let trimmed = fastq::Record::with_attrs(
+                record.id(),
+                record.desc(),
+                &record.seq()[..length],
+                &record.qual()[..length],
+            );
+            writer.write_record(&trimmed)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(read: &[u8], adapter: &[u8], error_rate: f64) -> Option<usize> {
+        let mut aligner = Aligner::new(GAP_OPEN, GAP_EXTEND, score);
+        find_adapter(read, adapter, &mut aligner, error_rate, 3)
+    }
+
+    #[test]
+    fn test_full_adapter() {
+        assert_eq!(
+            find(b"ACGTACGTAGATCGGAAGAGCTTTT", b"AGATCGGAAGAGC", 0.1),
+            Some(8)
+        );
+    }
+
+    #[test]
+    fn test_partial_adapter_at_end() {
+        assert_eq!(
+            find(b"ACGTACGTACGTAGATCGG", b"AGATCGGAAGAGC", 0.1),
+            Some(12)
+        );
+        // Too short an overlap to be trusted.
+        assert_eq!(find(b"CCCCCCCCCCCCCCCCCAG", b"AGATCGGAAGAGC", 0.1), None);
+    }
+
+    #[test]
+    fn test_error_rate() {
+        // One mismatch in 13 bases.
+        let read = b"ACGTACGTAGATCGCAAGAGCTTTT";
+        assert_eq!(find(read, b"AGATCGGAAGAGC", 0.1), Some(8));
+        assert_eq!(find(read, b"AGATCGGAAGAGC", 0.0), None);
+    }
+
+    #[test]
+    fn test_no_adapter() {
+        assert_eq!(find(b"ACGTACGTACGTACGTACGT", b"GGGGGGGGGG", 0.1), None);
+    }
+
+    #[test]
+    fn test_quality_trimming() {
+        // Qualities 40, 40, 10, 25, 2 with a cutoff of 20: the 25 among low qualities is trimmed
+        // too.
+        let qual = [73, 73, 43, 58, 35];
+        assert_eq!(quality_trimmed_length(&qual, 20), 2);
+        assert_eq!(quality_trimmed_length(&[73, 73, 73], 20), 3);
+        assert_eq!(quality_trimmed_length(&[35, 35], 20), 0);
+    }
+
+    #[test]
+    fn test_trim() {
+        let dir = std::env::temp_dir().join(format!("biotools-trim-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let reads = dir.join("reads.fastq");
+        std::fs::write(
+            &reads,
+            "@r1\nACGTACGTAGATCGGAAGAGCTTTT\n+\nIIIIIIIIIIIIIIIIIIIIIIIII\n\
+             @r2\nAGATCGGAAGAGC\n+\nIIIIIIIIIIIII\n",
+        )
+        .unwrap();
+        let opts = TrimOptions {
+            adapters: vec![b"AGATCGGAAGAGC".to_vec()],
+            error_rate: 0.1,
+            min_overlap: 3,
+            quality_cutoff: None,
+            min_length: 1,
+        };
+        let mut out = vec![];
+        trim(&reads, opts, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "@r1\nACGTACGT\n+\nIIIIIIII\n"
+        );
+
+        // Without an adapter or a quality cutoff there's nothing to trim.
+        let opts = TrimOptions {
+            adapters: vec![],
+            error_rate: 0.1,
+            min_overlap: 3,
+            quality_cutoff: None,
+            min_length: 0,
+        };
+        assert!(trim(&reads, opts, &mut vec![]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}